@@ -0,0 +1,87 @@
+//! Decodes a payload with many objects of the same class into structs
+//!
+//! Run with `cargo bench --bench objects`. Each object refers to the traits of
+//! the first one, so the member names are only read once, while every value
+//! is a new string that is added to the string reference table.
+//!
+//! The reference tables only hold borrowed strings and offsets, so a push is
+//! amortized constant time without an allocation of its own. It doesn't stand
+//! out next to decoding the values, which is why member names are not cached.
+
+use std::time::Instant;
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Item {
+    id: u32,
+    name: String,
+    price: f64,
+}
+
+/// `count` objects of the same class in an array
+fn payload(count: u32) -> Vec<u8> {
+    let mut input = vec![0x09];
+    push_u29(&mut input, count << 1 | 1);
+    input.push(0x01);
+    for i in 0..count {
+        input.push(0x0A);
+        if i == 0 {
+            // inline traits with three sealed members
+            input.extend_from_slice(b"\x33\x09Item\x05id\x09name\x0Bprice");
+        } else {
+            // the traits of the first object
+            input.push(0x01);
+        }
+        input.push(0x04);
+        push_u29(&mut input, i & 0x0FFF_FFFF);
+        let name = format!("item {}", i);
+        input.push(0x06);
+        push_u29(&mut input, (name.len() as u32) << 1 | 1);
+        input.extend_from_slice(name.as_bytes());
+        input.push(0x05);
+        input.extend_from_slice(&(f64::from(i) * 0.5).to_le_bytes());
+    }
+    input
+}
+
+fn push_u29(output: &mut Vec<u8>, value: u32) {
+    if value < 0x80 {
+        output.push(value as u8);
+    } else if value < 0x4000 {
+        output.extend_from_slice(&[(value >> 7) as u8 | 0x80, value as u8 & 0x7F]);
+    } else if value < 0x20_0000 {
+        output.extend_from_slice(&[
+            (value >> 14) as u8 | 0x80,
+            (value >> 7) as u8 | 0x80,
+            value as u8 & 0x7F,
+        ]);
+    } else {
+        output.extend_from_slice(&[
+            (value >> 22) as u8 | 0x80,
+            (value >> 15) as u8 | 0x80,
+            (value >> 8) as u8 | 0x80,
+            value as u8,
+        ]);
+    }
+}
+
+fn main() {
+    const ITERATIONS: u32 = 20;
+    for count in [1_000, 100_000] {
+        let input = payload(count);
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            let items = serde_amf3::deserialize::<Vec<Item>>(&input).unwrap();
+            assert_eq!(items.len(), count as usize);
+        }
+        let elapsed = start.elapsed() / ITERATIONS;
+        println!(
+            "{} objects: {:?} per decode, {:?} per object",
+            count,
+            elapsed,
+            elapsed / count
+        );
+    }
+}
@@ -0,0 +1,60 @@
+//! Decodes a memory-mapped AMF3 file, borrowing strings from the mapping
+
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use memmap2::Mmap;
+use serde_amf3::ValueRef;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Name of the file to decode
+    #[clap(value_parser)]
+    path: PathBuf,
+}
+
+/// Maps the whole file at `path` read-only
+fn open(path: &Path) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    // SAFETY: the file is not expected to change while it is decoded
+    unsafe { Mmap::map(&file) }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let map = open(&args.path).unwrap();
+    // every string in `value` points into `map`
+    let value = serde_amf3::deserialize::<ValueRef>(&map).unwrap();
+    println!("{:#?}", value);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_amf3::ValueRef;
+
+    #[test]
+    fn test_mapped_fixture() {
+        let path = std::env::temp_dir().join(format!("amf3-mmap-{}.amf3", std::process::id()));
+        std::fs::write(&path, b"\x09\x05\x01\x06\x0BHello\x06\x00").unwrap();
+        let map = super::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let value = serde_amf3::deserialize::<ValueRef>(&map).unwrap();
+        let ValueRef::Array(items) = value else {
+            panic!("expected an array, got {:?}", value);
+        };
+        for item in items {
+            let ValueRef::String(string) = item else {
+                panic!("expected a string, got {:?}", item);
+            };
+            assert_eq!(string, "Hello");
+            assert_eq!(string.as_ptr(), map[5..].as_ptr());
+        }
+    }
+}
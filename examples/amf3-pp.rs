@@ -1,7 +1,6 @@
 use std::path::PathBuf;
 
 use clap::Parser;
-use serde::Serialize;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -15,8 +14,8 @@ fn main() {
     let args = Args::parse();
 
     let bytes = std::fs::read(&args.path).unwrap();
-    let value = serde_amf3::deserialize::<serde_json::Value>(&bytes[..]).unwrap();
+    let mut deserializer = serde_amf3::ByteDeserializer::from_bytes(&bytes[..]);
     let mut serializer = serde_json::Serializer::pretty(std::io::stdout().lock());
-    value.serialize(&mut serializer).unwrap();
+    serde_transcode::transcode(&mut deserializer, &mut serializer).unwrap();
     println!();
 }
@@ -0,0 +1,77 @@
+//! Converts an AMF3 file to MessagePack
+//!
+//! The value is decoded into a [`Value`] first, which maps to MessagePack as follows:
+//!
+//! - `Undefined` and `Null` become nil
+//! - `Date` becomes a float of milliseconds since the epoch, like a `Double`
+//! - `Xml` and `XmlDoc` become strings
+//! - `ByteArray` becomes bin
+//! - `Array` becomes an array, or a map if it has associative keys
+//! - `Object` becomes a map of its members
+
+use std::{io::Write, path::PathBuf};
+
+use clap::Parser;
+use serde_amf3::Value;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Name of the AMF3 file to convert
+    #[clap(value_parser)]
+    path: PathBuf,
+    /// Name of the MessagePack file to write, stdout if missing
+    #[clap(value_parser)]
+    output: Option<PathBuf>,
+}
+
+fn convert(input: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let value = serde_amf3::deserialize::<Value>(input)?;
+    Ok(rmp_serde::to_vec(&value)?)
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let bytes = std::fs::read(&args.path).unwrap();
+    let output = convert(&bytes).unwrap();
+    match args.output {
+        Some(path) => std::fs::write(path, output).unwrap(),
+        None => std::io::stdout().lock().write_all(&output).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_amf3::Value;
+
+    #[test]
+    fn test_convert() {
+        // {a: [1, 0.25, "Hello"], b: true, c: null, d: <ByteArray 0xAB>, e: <Date 1000>}
+        let input = b"\x0A\x0B\x01\x03a\x09\x07\x01\x04\x01\x05\x00\x00\x00\x00\x00\x00\xD0\x3F\
+            \x06\x0BHello\x03b\x03\x03c\x01\x03d\x0C\x03\xAB\
+            \x03e\x08\x01\x00\x00\x00\x00\x00\x40\x8F\x40\x01";
+        let output = super::convert(input).unwrap();
+        // a map with 5 entries, the byte array as bin 8
+        assert_eq!(output[0], 0x85);
+        assert!(output.windows(3).any(|w| w == [0xC4, 0x01, 0xAB]));
+        let value = rmp_serde::from_slice::<Value>(&output).unwrap();
+        assert_eq!(
+            value,
+            Value::Object(vec![
+                (
+                    String::from("a"),
+                    Value::Array(vec![
+                        Value::Integer(1),
+                        Value::Double(0.25),
+                        Value::String(String::from("Hello")),
+                    ])
+                ),
+                (String::from("b"), Value::Bool(true)),
+                (String::from("c"), Value::Null),
+                (String::from("d"), Value::ByteArray(vec![0xAB])),
+                (String::from("e"), Value::Double(1000.0)),
+            ])
+        );
+    }
+}
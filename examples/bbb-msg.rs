@@ -11,21 +11,21 @@ struct Args {
     path: PathBuf,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 struct ExecutionStateStrip {
     #[serde(rename = "actionIndex")]
     action_index: u32,
     id: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 struct ExecutionState {
     #[serde(rename = "stateID")]
     state_id: u32,
     strips: Vec<ExecutionStateStrip>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 #[serde(tag = "Type")]
 enum Action {
     OnInteract {
@@ -46,26 +46,26 @@ enum Action {
     },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 struct Pos2 {
     x: u32,
     y: u32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 struct Strip {
     id: u32,
     actions: Vec<Action>,
     ui: Pos2,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 struct State {
     id: u32,
     strips: Vec<Strip>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 struct Root {
     #[serde(rename = "BehaviorID")]
     behavior_id: String,
@@ -83,3 +83,90 @@ fn main() {
     let value = serde_amf3::deserialize::<Root>(&bytes[..]).unwrap();
     println!("{:#?}", value);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, ExecutionState, ExecutionStateStrip, Pos2, Root, State, Strip};
+
+    fn fixture() -> Root {
+        Root {
+            behavior_id: String::from("10447"),
+            execution_state: ExecutionState {
+                state_id: 1,
+                strips: vec![
+                    ExecutionStateStrip {
+                        action_index: 0,
+                        id: 0,
+                    },
+                    ExecutionStateStrip {
+                        action_index: 2,
+                        id: 1,
+                    },
+                ],
+            },
+            object_id: String::from("70368747866196"),
+            states: vec![State {
+                id: 1,
+                strips: vec![
+                    Strip {
+                        id: 0,
+                        actions: vec![
+                            Action::OnInteract {
+                                callback_id: String::new(),
+                            },
+                            Action::FlyUp {
+                                distance: 25.5,
+                                callback_id: String::from("1"),
+                            },
+                        ],
+                        ui: Pos2 { x: 103, y: 82 },
+                    },
+                    Strip {
+                        id: 1,
+                        actions: vec![Action::FlyDown {
+                            distance: 25.5,
+                            callback_id: String::from("2"),
+                        }],
+                        ui: Pos2 { x: 420, y: 82 },
+                    },
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let root = fixture();
+        let bytes = serde_amf3::serialize(&root).unwrap();
+        assert_eq!(serde_amf3::deserialize::<Root>(&bytes), Ok(root));
+    }
+
+    #[test]
+    fn test_actions() {
+        let actions = [
+            Action::OnInteract {
+                callback_id: String::from("0"),
+            },
+            Action::FlyUp {
+                distance: 25.5,
+                callback_id: String::from("1"),
+            },
+            Action::FlyDown {
+                distance: -1.0,
+                callback_id: String::new(),
+            },
+        ];
+        for (action, name) in actions.iter().zip(["OnInteract", "FlyUp", "FlyDown"]) {
+            let bytes = serde_amf3::serialize(action).unwrap();
+            // an anonymous object, with the tag as the first member
+            let mut expected = b"\x0A\x0B\x01\x09Type\x06".to_vec();
+            expected.push((name.len() << 1 | 1) as u8);
+            expected.extend_from_slice(name.as_bytes());
+            assert_eq!(bytes[..expected.len()], expected);
+            assert_eq!(
+                serde_amf3::deserialize::<Action>(&bytes).as_ref(),
+                Ok(action)
+            );
+        }
+    }
+}
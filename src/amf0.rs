@@ -0,0 +1,447 @@
+//! Decoding of AMF0, the predecessor of AMF3 used by RTMP and Flash Remoting
+//!
+//! AMF0 streams may switch to AMF3 for a single value with the `avmplus-object`
+//! marker. That value is decoded by the regular [`ByteDeserializer`] which shares
+//! the position in the input.
+//!
+//! A reference to an earlier object or array is resolved by decoding that value
+//! again, as for AMF3.
+
+use serde::{de::Visitor, forward_to_deserialize_any, Deserialize};
+
+use super::{format, traits::VisitDouble, ByteDeserializer, Error};
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Marker {
+    Number,
+    Boolean,
+    String,
+    Object,
+    Null,
+    Undefined,
+    Reference,
+    EcmaArray,
+    StrictArray,
+    Date,
+    LongString,
+    Unsupported,
+    XmlDocument,
+    TypedObject,
+    AvmPlus,
+}
+
+impl Marker {
+    fn new(value: u8) -> Result<Self, format::Error> {
+        match value {
+            0x00 => Ok(Self::Number),
+            0x01 => Ok(Self::Boolean),
+            0x02 => Ok(Self::String),
+            0x03 => Ok(Self::Object),
+            0x05 => Ok(Self::Null),
+            0x06 => Ok(Self::Undefined),
+            0x07 => Ok(Self::Reference),
+            0x08 => Ok(Self::EcmaArray),
+            0x0A => Ok(Self::StrictArray),
+            0x0B => Ok(Self::Date),
+            0x0C => Ok(Self::LongString),
+            0x0D => Ok(Self::Unsupported),
+            0x0F => Ok(Self::XmlDocument),
+            0x10 => Ok(Self::TypedObject),
+            0x11 => Ok(Self::AvmPlus),
+            // movieclip, object-end, recordset are reserved or never a value
+            _ => Err(format::Error::InvalidMarker(value)),
+        }
+    }
+}
+
+const OBJECT_END: u8 = 0x09;
+
+/// An object or array that can be referenced, by the position of its marker
+struct Reference {
+    position: usize,
+    complete: bool,
+}
+
+pub struct Amf0Deserializer<'de> {
+    inner: ByteDeserializer<'de>,
+    references: Vec<Reference>,
+}
+
+impl<'de> Amf0Deserializer<'de> {
+    pub fn from_bytes(input: &'de [u8]) -> Self {
+        Self {
+            inner: ByteDeserializer::from_bytes(input),
+            references: Vec::new(),
+        }
+    }
+
+    fn format(&mut self) -> &mut format::Deserializer<'de> {
+        &mut self.inner.inner
+    }
+
+    fn read_utf8(&mut self) -> Result<&'de str, Error> {
+        let len = self.format().read_u16_be()?;
+        let bytes = self.format().read_bytes(len.into())?;
+        Ok(std::str::from_utf8(bytes).map_err(format::Error::from)?)
+    }
+
+    fn read_utf8_long(&mut self) -> Result<&'de str, Error> {
+        let len = self.format().read_u32_be()?;
+        let bytes = self.format().read_bytes(len as usize)?;
+        Ok(std::str::from_utf8(bytes).map_err(format::Error::from)?)
+    }
+
+    /// Decodes the value at `index` in the reference table again
+    fn deserialize_reference<V, F: VisitDouble>(
+        &mut self,
+        index: usize,
+        visitor: V,
+        amf3: fn(&mut ByteDeserializer<'de>, V) -> Result<V::Value, Error>,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let reference = self
+            .references
+            .get(index)
+            .ok_or(format::Error::MissingObjectReference)?;
+        if !reference.complete {
+            return Err(format::Error::CyclicReference.into());
+        }
+        let position = reference.position;
+        let checkpoint = self.format().seek(position)?;
+        let references = self.references.len();
+        let result = self.deserialize_into::<V, F>(visitor, amf3);
+        self.references.truncate(references);
+        self.format().restore(checkpoint);
+        result
+    }
+
+    fn deserialize_into<V, F: VisitDouble>(
+        &mut self,
+        visitor: V,
+        amf3: fn(&mut ByteDeserializer<'de>, V) -> Result<V::Value, Error>,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let position = self.format().position();
+        let marker = Marker::new(self.format().read_byte()?)?;
+        match marker {
+            Marker::Number => F::visit_double(visitor, self.format().read_double_be()?),
+            Marker::Boolean => visitor.visit_bool(self.format().read_byte()? != 0),
+            Marker::String => visitor.visit_borrowed_str(self.read_utf8()?),
+            Marker::Null => visitor.visit_unit(),
+            Marker::Undefined => visitor.visit_none(),
+            Marker::Reference => {
+                let index = self.format().read_u16_be()?;
+                self.deserialize_reference::<V, F>(index.into(), visitor, amf3)
+            }
+            Marker::Date => {
+                let millis = self.format().read_double_be()?;
+                // time-zone, reserved and should be zero
+                self.format().read_u16_be()?;
+                F::visit_double(visitor, millis)
+            }
+            Marker::LongString => visitor.visit_borrowed_str(self.read_utf8_long()?),
+            Marker::Unsupported => visitor.visit_unit(),
+            Marker::XmlDocument => visitor.visit_borrowed_str(self.read_utf8_long()?),
+            Marker::AvmPlus => {
+                // every switch to AMF3 starts with empty reference tables
+                self.format().reset_reference_tables();
+                amf3(&mut self.inner, visitor)
+            }
+            Marker::Object | Marker::EcmaArray | Marker::StrictArray | Marker::TypedObject => {
+                self.deserialize_complex(position, marker, visitor)
+            }
+        }
+    }
+
+    /// Decodes an object or array with its marker at `position`, which may be referenced later
+    fn deserialize_complex<V>(
+        &mut self,
+        position: usize,
+        marker: Marker,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.references.push(Reference {
+            position,
+            complete: false,
+        });
+        let index = self.references.len() - 1;
+        let result = self.deserialize_complex_body(marker, visitor);
+        self.references[index].complete = true;
+        result
+    }
+
+    fn deserialize_complex_body<V>(&mut self, marker: Marker, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match marker {
+            Marker::EcmaArray => {
+                // the count is only a hint, the entries are terminated like an object
+                self.format().read_u32_be()?;
+            }
+            Marker::StrictArray => {
+                let len = self.format().read_u32_be()?;
+                // every element takes at least its marker
+                let len = self.format().check_count(len, 1)?;
+                return visitor.visit_seq(Amf0Seq { inner: self, len });
+            }
+            Marker::TypedObject => {
+                let _class_name = self.read_utf8()?;
+            }
+            _ => {}
+        }
+        visitor.visit_map(Amf0Map { inner: self })
+    }
+}
+
+pub fn deserialize<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<T, Error> {
+    let mut deserializer = Amf0Deserializer::from_bytes(input);
+    T::deserialize(&mut deserializer)
+}
+
+struct Amf0Seq<'a, 'de> {
+    len: usize,
+    inner: &'a mut Amf0Deserializer<'de>,
+}
+
+impl<'a, 'de> serde::de::SeqAccess<'de> for Amf0Seq<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        if self.len > 0 {
+            self.len -= 1;
+            seed.deserialize(&mut *self.inner).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+struct Amf0Map<'a, 'de> {
+    inner: &'a mut Amf0Deserializer<'de>,
+}
+
+impl<'a, 'de> serde::de::MapAccess<'de> for Amf0Map<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        let key = self.inner.read_utf8()?;
+        if key.is_empty() {
+            let byte = self.inner.format().read_byte()?;
+            if byte == OBJECT_END {
+                Ok(None)
+            } else {
+                Err(format::Error::InvalidMarker(byte).into())
+            }
+        } else {
+            let deserializer = serde::de::value::BorrowedStrDeserializer::new(key);
+            seed.deserialize(deserializer).map(Some)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.inner)
+    }
+}
+
+impl<'de> serde::Deserializer<'de> for &mut Amf0Deserializer<'de> {
+    type Error = Error;
+
+    forward_to_deserialize_any! {
+        bool char str string bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, f64>(visitor, |de, v| {
+            serde::Deserializer::deserialize_any(de, v)
+        })
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match Marker::new(self.format().peek_byte()?)? {
+            Marker::Null | Marker::Undefined => {
+                self.format().read_byte()?;
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, i8>(visitor, |de, v| serde::Deserializer::deserialize_i8(de, v))
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, i16>(visitor, |de, v| {
+            serde::Deserializer::deserialize_i16(de, v)
+        })
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, i32>(visitor, |de, v| {
+            serde::Deserializer::deserialize_i32(de, v)
+        })
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, i64>(visitor, |de, v| {
+            serde::Deserializer::deserialize_i64(de, v)
+        })
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, u8>(visitor, |de, v| serde::Deserializer::deserialize_u8(de, v))
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, u16>(visitor, |de, v| {
+            serde::Deserializer::deserialize_u16(de, v)
+        })
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, u32>(visitor, |de, v| {
+            serde::Deserializer::deserialize_u32(de, v)
+        })
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, u64>(visitor, |de, v| {
+            serde::Deserializer::deserialize_u64(de, v)
+        })
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, f32>(visitor, |de, v| {
+            serde::Deserializer::deserialize_f32(de, v)
+        })
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_into::<V, f64>(visitor, |de, v| {
+            serde::Deserializer::deserialize_f64(de, v)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::format;
+
+    #[derive(Deserialize, Debug, Clone, PartialEq)]
+    struct Command<'a> {
+        name: &'a str,
+        id: u32,
+    }
+
+    #[test]
+    fn test_number() {
+        assert_eq!(
+            super::deserialize(&[0x00, 0x40, 0x14, 0, 0, 0, 0, 0, 0]),
+            Ok(5u32)
+        );
+    }
+
+    #[test]
+    fn test_reference() {
+        // the strict array has index 0, the object has index 1
+        let input = [
+            0x0A, 0, 0, 0, 2, 0x03, 0, 4, b'n', b'a', b'm', b'e', 0x02, 0, 2, b'o', b'k', 0, 2,
+            b'i', b'd', 0x00, 0x40, 0x1C, 0, 0, 0, 0, 0, 0, 0, 0, 0x09, 0x07, 0, 1,
+        ];
+        assert_eq!(
+            super::deserialize(&input),
+            Ok(vec![Command { name: "ok", id: 7 }; 2])
+        );
+        // the array refers to itself
+        let input = [0x0A, 0, 0, 0, 1, 0x07, 0, 0];
+        assert!(super::deserialize::<Vec<Vec<u32>>>(&input).is_err());
+        assert!(super::deserialize::<Vec<u32>>(&[0x07, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_strict_array_count() {
+        let input = [0x0A, 0xFF, 0xFF, 0xFF, 0xFF, 0x05];
+        assert_eq!(
+            super::deserialize::<Vec<()>>(&input),
+            Err(format::Error::EndOfStream.into())
+        );
+    }
+
+    #[test]
+    fn test_avmplus_integer() {
+        assert_eq!(super::deserialize(&[0x11, 0x04, 0x05]), Ok(5u32));
+        // the AMF3 value is followed by an AMF0 number
+        let input = [
+            0x0A, 0, 0, 0, 2, 0x11, 0x04, 0x05, 0x00, 0x3F, 0xF0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(super::deserialize(&input), Ok(vec![5u32, 1]));
+    }
+
+    #[test]
+    fn test_avmplus_in_object() {
+        let input = [
+            0x03, 0, 4, b'n', b'a', b'm', b'e', 0x02, 0, 2, b'o', b'k', 0, 2, b'i', b'd', 0x11,
+            0x04, 0x07, 0, 0, 0x09,
+        ];
+        assert_eq!(
+            super::deserialize(&input),
+            Ok(Command { name: "ok", id: 7 })
+        );
+    }
+}
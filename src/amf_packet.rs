@@ -0,0 +1,229 @@
+//! Framing of AMF packets, as sent by Flash Remoting
+//!
+//! A packet starts with a version, followed by a list of headers and a list of
+//! bodies (messages). Each body names its target and response URIs and wraps a
+//! single AMF0 value, which switches to AMF3 with the `avmplus-object` marker.
+//! The [`PacketReader`] skips the headers and yields the raw bytes of each body
+//! for [`crate::amf0::deserialize`]. The [`PacketWriter`] frames values from
+//! [`crate::serialize`] as the bodies of a packet without headers.
+//!
+//! ```
+//! # use serde_amf3::amf_packet::PacketReader;
+//! let input = b"\0\x03\0\0\0\x01\0\x04echo\0\x02/1\0\0\0\x03\x11\x04\x05";
+//! for body in PacketReader::from_bytes(input)? {
+//!     let body = body?;
+//!     assert_eq!(body.target_uri, "echo");
+//!     assert_eq!(serde_amf3::amf0::deserialize::<u32>(body.bytes)?, 5);
+//! }
+//! # Ok::<(), serde_amf3::Error>(())
+//! ```
+
+use serde::Serialize;
+
+use super::{format, options::DeserializerOptions, Error};
+
+/// The AMF0 marker that switches to AMF3 for the next value
+const AVMPLUS_MARKER: u8 = 0x11;
+
+/// A single message in an AMF packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Body<'de> {
+    /// The method to invoke, or the response URI of the request for a reply
+    pub target_uri: &'de str,
+    /// Where to send the reply to, e.g. `/1`
+    pub response_uri: &'de str,
+    /// The AMF0 encoded value of this message
+    pub bytes: &'de [u8],
+}
+
+/// Reads the bodies of an AMF packet in order
+pub struct PacketReader<'de> {
+    inner: format::Deserializer<'de>,
+    version: u16,
+    /// The number of bodies that were not read yet
+    remaining: u16,
+}
+
+impl<'de> PacketReader<'de> {
+    /// Reads the version and skips the headers of the packet in `input`
+    pub fn from_bytes(input: &'de [u8]) -> Result<Self, Error> {
+        let mut inner = format::Deserializer::with_options(input, DeserializerOptions::default());
+        let version = inner.read_u16_be()?;
+        let header_count = inner.read_u16_be()?;
+        for _ in 0..header_count {
+            let _name = read_utf8(&mut inner)?;
+            let _must_understand = inner.read_byte()?;
+            let len = inner.read_u32_be()?;
+            inner.read_bytes(len as usize)?;
+        }
+        let remaining = inner.read_u16_be()?;
+        Ok(Self {
+            inner,
+            version,
+            remaining,
+        })
+    }
+
+    /// The version of the packet, `0` for AMF0 only and `3` if values may use AMF3
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    fn read_body(&mut self) -> Result<Body<'de>, Error> {
+        let target_uri = read_utf8(&mut self.inner)?;
+        let response_uri = read_utf8(&mut self.inner)?;
+        let len = self.inner.read_u32_be()?;
+        let bytes = self.inner.read_bytes(len as usize)?;
+        Ok(Body {
+            target_uri,
+            response_uri,
+            bytes,
+        })
+    }
+}
+
+impl<'de> Iterator for PacketReader<'de> {
+    type Item = Result<Body<'de>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let body = self.read_body();
+        // the framing of the remaining bodies is lost after an error
+        self.remaining = if body.is_ok() { self.remaining - 1 } else { 0 };
+        Some(body)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining.into()))
+    }
+}
+
+/// Writes the bodies of an AMF packet in order
+pub struct PacketWriter {
+    output: Vec<u8>,
+    /// The number of bodies written so far
+    count: u16,
+}
+
+impl PacketWriter {
+    /// Starts a packet with `version` and no headers
+    pub fn new(version: u16) -> Self {
+        let mut output = Vec::new();
+        output.extend_from_slice(&version.to_be_bytes());
+        // no headers, the number of bodies is filled in by `finish`
+        output.extend_from_slice(&[0, 0, 0, 0]);
+        Self { output, count: 0 }
+    }
+
+    /// Adds a body with an AMF0 encoded value
+    pub fn write_body(
+        &mut self,
+        target_uri: &str,
+        response_uri: &str,
+        bytes: &[u8],
+    ) -> Result<(), Error> {
+        // check everything first, so that a failed body leaves no trace
+        let target_len = utf8_len(target_uri)?;
+        let response_len = utf8_len(response_uri)?;
+        let len = u32::try_from(bytes.len()).map_err(|_| format::Error::LengthTooLarge)?;
+        let count = self
+            .count
+            .checked_add(1)
+            .ok_or(format::Error::LengthTooLarge)?;
+        self.output.extend_from_slice(&target_len.to_be_bytes());
+        self.output.extend_from_slice(target_uri.as_bytes());
+        self.output.extend_from_slice(&response_len.to_be_bytes());
+        self.output.extend_from_slice(response_uri.as_bytes());
+        self.output.extend_from_slice(&len.to_be_bytes());
+        self.output.extend_from_slice(bytes);
+        self.count = count;
+        Ok(())
+    }
+
+    /// Adds a body with `value` encoded as AMF3, after the marker that switches to it
+    pub fn write_amf3<T: Serialize + ?Sized>(
+        &mut self,
+        target_uri: &str,
+        response_uri: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let mut bytes = vec![AVMPLUS_MARKER];
+        bytes.append(&mut crate::serialize(value)?);
+        self.write_body(target_uri, response_uri, &bytes)
+    }
+
+    /// The packet with all bodies written so far
+    pub fn finish(mut self) -> Vec<u8> {
+        self.output[4..6].copy_from_slice(&self.count.to_be_bytes());
+        self.output
+    }
+}
+
+fn utf8_len(value: &str) -> Result<u16, Error> {
+    Ok(u16::try_from(value.len()).map_err(|_| format::Error::LengthTooLarge)?)
+}
+
+fn read_utf8<'de>(inner: &mut format::Deserializer<'de>) -> Result<&'de str, Error> {
+    let len = inner.read_u16_be()?;
+    let bytes = inner.read_bytes(len.into())?;
+    Ok(std::str::from_utf8(bytes).map_err(format::Error::from)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Body, PacketReader, PacketWriter};
+
+    #[test]
+    fn test_single_body() {
+        let input = b"\0\x03\0\x01\0\x04auth\x01\0\0\0\x02\x01\x01\0\x01\0\x0bapp.getUser\0\x02/1\0\0\0\x04\x11\x06\x03a";
+        let mut reader = PacketReader::from_bytes(input).unwrap();
+        assert_eq!(reader.version(), 3);
+        let body = reader.next().unwrap().unwrap();
+        assert_eq!(
+            body,
+            Body {
+                target_uri: "app.getUser",
+                response_uri: "/1",
+                bytes: b"\x11\x06\x03a",
+            }
+        );
+        assert_eq!(crate::amf0::deserialize::<&str>(body.bytes), Ok("a"));
+        assert_eq!(reader.next(), None);
+    }
+
+    #[test]
+    fn test_write_packet() {
+        let mut writer = PacketWriter::new(3);
+        writer.write_amf3("app.getUser", "/1", &[1u32, 2]).unwrap();
+        writer.write_body("echo", "/2", b"\x02\0\x01a").unwrap();
+        let packet = writer.finish();
+
+        let mut reader = PacketReader::from_bytes(&packet).unwrap();
+        assert_eq!(reader.version(), 3);
+        let body = reader.next().unwrap().unwrap();
+        assert_eq!((body.target_uri, body.response_uri), ("app.getUser", "/1"));
+        assert_eq!(
+            crate::amf0::deserialize::<Vec<u32>>(body.bytes),
+            Ok(vec![1, 2])
+        );
+        let body = reader.next().unwrap().unwrap();
+        assert_eq!((body.target_uri, body.response_uri), ("echo", "/2"));
+        assert_eq!(crate::amf0::deserialize::<&str>(body.bytes), Ok("a"));
+        assert_eq!(reader.next(), None);
+
+        let mut writer = PacketWriter::new(0);
+        let long = "a".repeat(1 << 16);
+        assert!(writer.write_body("echo", &long, b"").is_err());
+        assert_eq!(writer.finish(), b"\0\0\0\0\0\0");
+    }
+
+    #[test]
+    fn test_truncated_body() {
+        let input = b"\0\x03\0\0\0\x02\0\x01a\0\x02/1\0\0\0\x05\x11";
+        let mut reader = PacketReader::from_bytes(input).unwrap();
+        assert!(matches!(reader.next(), Some(Err(_))));
+        assert_eq!(reader.next(), None);
+    }
+}
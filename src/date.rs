@@ -0,0 +1,175 @@
+//! Conversion of the AMF3 `Date` marker from and to [`SystemTime`]
+//!
+//! AMF3 stores dates as a double of milliseconds since the unix epoch, which
+//! is negative for dates before 1970.
+//!
+//! ```
+//! # use std::time::SystemTime;
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Event {
+//!     #[serde(with = "serde_amf3::date")]
+//!     time: SystemTime,
+//! }
+//! ```
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::value::DATE_TOKEN;
+
+/// Converts milliseconds since the epoch into a [`SystemTime`]
+pub fn from_millis(millis: f64) -> Option<SystemTime> {
+    let abs = millis.abs();
+    if abs.is_nan() || abs >= u64::MAX as f64 {
+        return None;
+    }
+    // whole milliseconds are exact, unlike a division into seconds
+    let whole = abs.trunc();
+    let nanos = ((abs - whole) * 1_000_000.0).round() as u64;
+    let duration = Duration::from_millis(whole as u64) + Duration::from_nanos(nanos);
+    if millis < 0.0 {
+        UNIX_EPOCH.checked_sub(duration)
+    } else {
+        UNIX_EPOCH.checked_add(duration)
+    }
+}
+
+/// Converts a [`SystemTime`] into milliseconds since the epoch, negative before it
+pub fn to_millis(time: SystemTime) -> f64 {
+    let millis = |duration: Duration| {
+        duration.as_millis() as f64 + f64::from(duration.subsec_nanos() % 1_000_000) / 1_000_000.0
+    };
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => millis(duration),
+        Err(e) => -millis(e.duration()),
+    }
+}
+
+pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_struct(DATE_TOKEN, &to_millis(*time))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+    let millis = f64::deserialize(deserializer)?;
+    from_millis(millis)
+        .ok_or_else(|| serde::de::Error::custom(format!("date out of range: {}ms", millis)))
+}
+
+/// A date for display, which serializes as an RFC 3339 string
+///
+/// ```
+/// # #[cfg(feature = "chrono")] {
+/// let input = b"\x08\x01\0\0\0\0\0\0\0\0";
+/// let date: serde_amf3::date::IsoDate = serde_amf3::deserialize(input).unwrap();
+/// assert_eq!(date.to_rfc3339(), "1970-01-01T00:00:00+00:00");
+/// # }
+/// ```
+#[cfg(feature = "chrono")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IsoDate(pub chrono::DateTime<chrono::Utc>);
+
+#[cfg(feature = "chrono")]
+impl IsoDate {
+    /// Converts milliseconds since the epoch, dropping any fraction of a millisecond
+    pub fn from_millis(millis: f64) -> Option<Self> {
+        if millis.is_finite() {
+            chrono::DateTime::from_timestamp_millis(millis as i64).map(Self)
+        } else {
+            None
+        }
+    }
+
+    pub fn to_rfc3339(&self) -> String {
+        self.0.to_rfc3339()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<'de> Deserialize<'de> for IsoDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let millis = f64::deserialize(deserializer)?;
+        Self::from_millis(millis)
+            .ok_or_else(|| serde::de::Error::custom(format!("date out of range: {}ms", millis)))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl serde::Serialize for IsoDate {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_rfc3339())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Event {
+        #[serde(with = "super")]
+        time: SystemTime,
+    }
+
+    fn date(millis: f64) -> Vec<u8> {
+        let mut bytes = vec![0x08, 0x01];
+        bytes.extend_from_slice(&millis.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_pre_epoch() {
+        let input = date(-86_400_500.0);
+        assert_eq!(crate::deserialize::<f64>(&input), Ok(-86_400_500.0));
+
+        let mut object = b"\x0A\x13\x01\x09time".to_vec();
+        object.extend_from_slice(&input);
+        assert_eq!(
+            crate::deserialize::<Event>(&object),
+            Ok(Event {
+                time: UNIX_EPOCH - Duration::from_millis(86_400_500)
+            })
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        for time in [
+            UNIX_EPOCH + Duration::from_millis(1_000_000_000_123),
+            UNIX_EPOCH - Duration::from_millis(86_400_500),
+        ] {
+            let event = Event { time };
+            let bytes = crate::serialize(&event).unwrap();
+            let mut expected = b"\x0A\x0B\x01\x09time".to_vec();
+            expected.extend_from_slice(&date(super::to_millis(time)));
+            expected.push(0x01);
+            assert_eq!(bytes, expected);
+            assert_eq!(crate::deserialize(&bytes), Ok(event));
+        }
+    }
+
+    #[test]
+    fn test_post_epoch() {
+        assert_eq!(
+            super::from_millis(1_000_000_000_000.0),
+            Some(UNIX_EPOCH + Duration::from_secs(1_000_000_000))
+        );
+        assert_eq!(super::from_millis(f64::NAN), None);
+        assert_eq!(super::from_millis(f64::NEG_INFINITY), None);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_iso_date() {
+        let input = date(1_000_000_000_123.0);
+        let value = crate::deserialize::<super::IsoDate>(&input).unwrap();
+        assert_eq!(value.to_rfc3339(), "2001-09-09T01:46:40.123+00:00");
+        assert_eq!(
+            crate::serialize(&value),
+            crate::serialize("2001-09-09T01:46:40.123+00:00")
+        );
+        assert!(crate::deserialize::<super::IsoDate>(&date(f64::INFINITY)).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+//! Conversion of a [`Duration`] from and to a number of milliseconds
+//!
+//! Whole milliseconds are written as an `Integer` where possible, and decoding
+//! accepts both an `Integer` and a `Double` with a fractional part.
+//!
+//! ```
+//! # use std::time::Duration;
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Animation {
+//!     #[serde(with = "serde_amf3::duration_millis")]
+//!     length: Duration,
+//! }
+//! ```
+
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Converts a non-negative number of milliseconds into a [`Duration`]
+pub fn from_millis(millis: f64) -> Option<Duration> {
+    if millis.is_nan() || millis < 0.0 || millis >= u64::MAX as f64 {
+        return None;
+    }
+    let whole = millis.trunc();
+    let nanos = ((millis - whole) * 1_000_000.0).round() as u64;
+    Some(Duration::from_millis(whole as u64) + Duration::from_nanos(nanos))
+}
+
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    if duration.subsec_nanos() == duration.subsec_millis() * 1_000_000 {
+        // written as an integer if it is small enough
+        serializer.serialize_u64(duration.as_millis() as u64)
+    } else {
+        serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let millis = f64::deserialize(deserializer)?;
+    from_millis(millis)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid duration: {}ms", millis)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Millis(#[serde(with = "super")] Duration);
+
+    #[test]
+    fn test_integer() {
+        let value = Millis(Duration::from_millis(1500));
+        let bytes = crate::serialize(&value).unwrap();
+        assert_eq!(bytes, [0x04, 0x8B, 0x5C]);
+        assert_eq!(crate::deserialize(&bytes), Ok(value));
+    }
+
+    #[test]
+    fn test_fractional() {
+        let value = Millis(Duration::from_micros(2500));
+        let bytes = crate::serialize(&value).unwrap();
+        let mut expected = vec![0x05];
+        expected.extend_from_slice(&2.5f64.to_le_bytes());
+        assert_eq!(bytes, expected);
+        assert_eq!(crate::deserialize(&bytes), Ok(value));
+    }
+
+    #[test]
+    fn test_negative() {
+        assert!(crate::deserialize::<Millis>(&[0x04, 0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+        let mut input = vec![0x05];
+        input.extend_from_slice(&(-0.5f64).to_le_bytes());
+        assert!(crate::deserialize::<Millis>(&input).is_err());
+    }
+}
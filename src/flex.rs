@@ -0,0 +1,113 @@
+//! The common message classes of Flex RPC, from `flex.messaging.messages`
+//!
+//! Flex remoting and messaging wrap every request and reply in one of these
+//! classes. They are encoded as typed objects with sealed members, so they
+//! decode like any other struct:
+//!
+//! ```
+//! # use serde_amf3::flex::AcknowledgeMessage;
+//! let input = b"\x0A\x0B\x01\x13messageId\x06\x07ABC\x01";
+//! let message: AcknowledgeMessage = serde_amf3::deserialize(input)?;
+//! assert_eq!(message.message_id, "ABC");
+//! # Ok::<(), serde_amf3::Error>(())
+//! ```
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Value;
+
+/// The reply to a successful request, `flex.messaging.messages.AcknowledgeMessage`
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AcknowledgeMessage {
+    pub body: Value,
+    pub client_id: Option<String>,
+    /// The `message_id` of the request
+    pub correlation_id: String,
+    pub destination: String,
+    pub headers: HashMap<String, Value>,
+    pub message_id: String,
+    /// Milliseconds before the message expires, `0` for never
+    pub time_to_live: f64,
+    /// Milliseconds since the epoch when the message was sent
+    pub timestamp: f64,
+}
+
+/// A request to the messaging infrastructure itself, `flex.messaging.messages.CommandMessage`
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommandMessage {
+    pub body: Value,
+    pub client_id: Option<String>,
+    pub correlation_id: String,
+    pub destination: String,
+    pub headers: HashMap<String, Value>,
+    pub message_id: String,
+    /// What to do, e.g. [`CommandMessage::CLIENT_PING_OPERATION`]
+    pub operation: i32,
+    pub time_to_live: f64,
+    pub timestamp: f64,
+}
+
+impl CommandMessage {
+    pub const SUBSCRIBE_OPERATION: i32 = 0;
+    pub const UNSUBSCRIBE_OPERATION: i32 = 1;
+    pub const POLL_OPERATION: i32 = 2;
+    pub const CLIENT_PING_OPERATION: i32 = 5;
+    pub const LOGIN_OPERATION: i32 = 8;
+    pub const LOGOUT_OPERATION: i32 = 9;
+    pub const DISCONNECT_OPERATION: i32 = 12;
+}
+
+/// A call of a remote method, `flex.messaging.messages.RemotingMessage`
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RemotingMessage {
+    /// The arguments of the call, usually an array
+    pub body: Value,
+    pub client_id: Option<String>,
+    pub destination: String,
+    pub headers: HashMap<String, Value>,
+    pub message_id: String,
+    /// The name of the method
+    pub operation: String,
+    /// The class of the service, if not configured for the destination
+    pub source: Option<String>,
+    pub time_to_live: f64,
+    pub timestamp: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::CommandMessage;
+    use crate::Value;
+
+    #[test]
+    fn test_command_message() {
+        let input = b"\x0A\x81\x13Mflex.messaging.messages.CommandMessage\x09body\x11clientId\
+            \x1BcorrelationId\x17destination\x0Fheaders\x13messageId\x13operation\x15timeToLive\
+            \x13timestamp\x0A\x0B\x01\x01\x01\x06\x01\x06\x01\x0A\x0B\x01\x09DSId\x06\x07nil\x01\
+            \x06\x07ABC\x04\x05\x04\x00\x04\x00";
+        assert_eq!(
+            crate::deserialize::<CommandMessage>(input),
+            Ok(CommandMessage {
+                body: Value::Object(vec![]),
+                client_id: None,
+                correlation_id: String::new(),
+                destination: String::new(),
+                headers: HashMap::from([(
+                    String::from("DSId"),
+                    Value::String(String::from("nil"))
+                )]),
+                message_id: String::from("ABC"),
+                operation: CommandMessage::CLIENT_PING_OPERATION,
+                time_to_live: 0.0,
+                timestamp: 0.0,
+            })
+        );
+    }
+}
@@ -1,8 +1,10 @@
 use std::str::Utf8Error;
 
+use super::options::{DeserializerOptions, Endianness};
+
+/// The type marker that precedes every AMF3 value
 #[repr(u8)]
-#[derive(Copy, Clone, PartialEq, Eq)]
-#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Marker {
     Undefined = 0x00,
     Null = 0x01,
@@ -25,21 +27,37 @@ pub enum Marker {
 }
 
 impl Marker {
-    fn new(value: u8) -> Result<Self, Error> {
+    pub(super) fn new(value: u8) -> Result<Self, Error> {
         if value < 0x12 {
-            Ok(unsafe { std::mem::transmute(value) })
+            Ok(unsafe { std::mem::transmute::<u8, Marker>(value) })
         } else {
             Err(Error::InvalidMarker(value))
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) enum Error {
     InvalidMarker(u8),
     StringDecode(Utf8Error),
     EndOfStream,
     MissingStringReference,
+    MissingTraitReference,
+    MissingObjectReference,
+    CyclicReference,
+    ClassNameByReference,
+    /// Traits that are externalizable and also dynamic or with sealed members
+    InvalidTraits,
+    /// An externalizable class with a body that can't be skipped
+    Externalizable(String),
+    ReferencesDisabled,
+    WorkBudgetExceeded,
+    LengthTooLarge,
+    U29OutOfRange(u32),
+    /// A boolean flag byte, like the fixed flag of a vector, that is neither 0 nor 1
+    InvalidFlag(u8),
+    /// A read past [`DeserializerOptions::input_limit`], before the end of the input
+    InputLimitExceeded,
 }
 
 impl From<Utf8Error> for Error {
@@ -48,10 +66,93 @@ impl From<Utf8Error> for Error {
     }
 }
 
+/// The flags of the `U29O` header of an object, after the marker
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) struct ObjectHeader {
+    /// The object is the one at `count_or_index` in the object table
+    pub is_ref: bool,
+    /// The traits are the ones at `count_or_index` in the traits table
+    pub traits_ref: bool,
+    pub externalizable: bool,
+    pub dynamic: bool,
+    /// The index of a reference, or the number of sealed members for inline traits
+    pub count_or_index: u32,
+}
+
+impl ObjectHeader {
+    pub(super) fn new(header: u32) -> Self {
+        let is_ref = header & 1 == 0;
+        let traits_ref = !is_ref && header & 0b10 == 0;
+        let inline_traits = !is_ref && !traits_ref;
+        Self {
+            is_ref,
+            traits_ref,
+            externalizable: inline_traits && header & 0b100 != 0,
+            dynamic: inline_traits && header & 0b1000 != 0,
+            count_or_index: if is_ref {
+                header >> 1
+            } else if traits_ref {
+                header >> 2
+            } else {
+                header >> 4
+            },
+        }
+    }
+}
+
+/// The class definition shared by all instances of a class
+pub(super) struct Traits<'de> {
+    pub class_name: &'de str,
+    pub members: Vec<&'de str>,
+    pub dynamic: bool,
+    pub externalizable: bool,
+}
+
+/// A complex value, which can be decoded again from its position in the input
+struct ObjectReference {
+    position: usize,
+    complete: bool,
+}
+
+/// The contents of the reference tables, in the order of their indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceManifest<'de> {
+    /// The non-empty strings, including member names and class names
+    pub strings: Vec<&'de str>,
+    /// The class name of each traits, empty for anonymous objects
+    pub traits: Vec<&'de str>,
+    /// The offset of the marker of each complex value in the input
+    pub objects: Vec<usize>,
+}
+
+/// The state to return to after decoding a value by reference
+pub(super) struct Checkpoint<'de> {
+    input: std::slice::Iter<'de, u8>,
+    strings: usize,
+    traits: usize,
+    objects: usize,
+}
+
+/// The position in the input and the reference tables of an AMF3 stream
+///
+/// This is the state that a [`ByteDeserializer`](crate::ByteDeserializer) decodes values
+/// with. It can be taken out with [`into_format`](crate::ByteDeserializer::into_format)
+/// to read the framing of a custom container, and then continue with
+/// [`from_format`](crate::ByteDeserializer::from_format), keeping all references intact.
 pub struct Deserializer<'de> {
+    bytes: &'de [u8],
     input: std::slice::Iter<'de, u8>,
 
     string_reference_table: Vec<&'de str>,
+    trait_reference_table: Vec<Traits<'de>>,
+    object_reference_table: Vec<ObjectReference>,
+
+    /// The input was cut off at [`DeserializerOptions::input_limit`]
+    limited: bool,
+
+    pub(super) options: DeserializerOptions,
+    /// How many more times a value may be decoded again for a reference
+    pub(super) rewalk_budget: usize,
 }
 
 fn try_split_array_ref<const N: usize>(slice: &[u8]) -> Result<(&[u8; N], &[u8]), Error> {
@@ -65,20 +166,98 @@ fn try_split_array_ref<const N: usize>(slice: &[u8]) -> Result<(&[u8; N], &[u8])
 }
 
 impl<'de> Deserializer<'de> {
+    /// The error for a read past the end of the (limited) input
+    fn end_of_stream(&self) -> Error {
+        if self.limited {
+            Error::InputLimitExceeded
+        } else {
+            Error::EndOfStream
+        }
+    }
+
     pub(super) fn read_byte(&mut self) -> Result<u8, Error> {
-        self.input.next().copied().ok_or(Error::EndOfStream)
+        match self.input.next() {
+            Some(byte) => Ok(*byte),
+            None => Err(self.end_of_stream()),
+        }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.input.len() == 0
+    }
+
+    /// The input after the current position
+    pub fn remaining(&self) -> &'de [u8] {
+        self.input.as_slice()
+    }
+
+    /// Checks a count of items that each take at least `item_size` more bytes of input,
+    /// so that a crafted header fails before anything is allocated for it
+    pub(super) fn check_count(&self, count: u32, item_size: usize) -> Result<usize, Error> {
+        let count = count as usize;
+        let len = count.checked_mul(item_size).ok_or(Error::LengthTooLarge)?;
+        if len > self.input.len() {
+            Err(self.end_of_stream())
+        } else {
+            Ok(count)
+        }
+    }
+
+    /// Reads a byte that is either `0` (false) or `1` (true)
+    pub(super) fn read_flag(&mut self) -> Result<bool, Error> {
+        match self.read_byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(Error::InvalidFlag(byte)),
+        }
+    }
+
+    pub(super) fn peek_byte(&self) -> Result<u8, Error> {
+        self.input
+            .as_slice()
+            .first()
+            .copied()
+            .ok_or_else(|| self.end_of_stream())
     }
 
     pub(super) fn read_marker(&mut self) -> Result<Marker, Error> {
         let byte = self.read_byte()?;
-        Marker::new(byte)
+        match Marker::new(byte) {
+            Err(Error::InvalidMarker(byte)) if self.options.unknown_markers.is_some() => {
+                let len = self.unknown_marker_len(byte, self.input.as_slice())?;
+                self.read_bytes(len)?;
+                Ok(Marker::Undefined)
+            }
+            result => result,
+        }
+    }
+
+    /// Checks the marker of the next value without consuming it, like [`Self::read_marker`]
+    pub(super) fn peek_marker(&self) -> Result<Marker, Error> {
+        let byte = self.peek_byte()?;
+        match Marker::new(byte) {
+            Err(Error::InvalidMarker(byte)) if self.options.unknown_markers.is_some() => {
+                // the handler may still reject it
+                self.unknown_marker_len(byte, &self.input.as_slice()[1..])?;
+                Ok(Marker::Undefined)
+            }
+            result => result,
+        }
+    }
+
+    /// Asks the handler of unknown markers how long the value in `input` after `byte` is
+    fn unknown_marker_len(&self, byte: u8, input: &[u8]) -> Result<usize, Error> {
+        self.options
+            .unknown_markers
+            .and_then(|handler| handler(byte, input))
+            .ok_or(Error::InvalidMarker(byte))
     }
 
     /// 0x00000000 - 0x0000007F : 0xxxxxxx
     /// 0x00000080 - 0x00003FFF : 1xxxxxxx 0xxxxxxx
     /// 0x00004000 - 0x001FFFFF : 1xxxxxxx 1xxxxxxx 0xxxxxxx
-    /// 0x00200000 - 0x3FFFFFFF : 1xxxxxxx 1xxxxxxx 1xxxxxxx xxxxxxxx
-    /// 0x40000000 - 0xFFFFFFFF : throw range exception
+    /// 0x00200000 - 0x1FFFFFFF : 1xxxxxxx 1xxxxxxx 1xxxxxxx xxxxxxxx
+    /// 0x20000000 - 0xFFFFFFFF : throw range exception
     pub(super) fn read_u29(&mut self) -> Result<u32, Error> {
         let first = self.read_byte()?;
         let mut value = u32::from(first & 0x7F);
@@ -100,74 +279,589 @@ impl<'de> Deserializer<'de> {
         Ok(value)
     }
 
+    /// Reads a `u29` as a signed 29-bit integer, like the value of [`Marker::Integer`]
+    pub(super) fn read_i29(&mut self) -> Result<i32, Error> {
+        let value = self.read_u29()?;
+        Ok(((value << 3) as i32) >> 3)
+    }
+
+    /// Reads the 8 bytes of a double, without checking that a [`Marker::Double`]
+    /// (or a marker that is followed by a double) was read before
+    ///
+    /// The byte order is the one from [`DeserializerOptions::double_endianness`].
     pub(super) fn read_double(&mut self) -> Result<f64, Error> {
+        match self.options.double_endianness {
+            Endianness::Little => {
+                let double_bytes = self.read_array()?;
+                Ok(f64::from_le_bytes(*double_bytes))
+            }
+            Endianness::Big => self.read_double_be(),
+        }
+    }
+
+    /// Reads an element of `Vector.<int>` or `Vector.<uint>`, always little-endian
+    ///
+    /// Unlike [`Self::read_double`], this ignores [`DeserializerOptions::double_endianness`].
+    pub(super) fn read_u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.read_array()?;
+        Ok(u32::from_le_bytes(*bytes))
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<&'de [u8; N], Error> {
         let slice = self.input.as_slice();
-        let (double_bytes, rest) = try_split_array_ref(slice)?;
+        let (bytes, rest) = try_split_array_ref(slice).map_err(|_| self.end_of_stream())?;
         self.input = rest.iter();
-        Ok(f64::from_le_bytes(*double_bytes))
+        Ok(bytes)
+    }
+
+    pub(super) fn read_u16_be(&mut self) -> Result<u16, Error> {
+        let bytes = self.read_array()?;
+        Ok(u16::from_be_bytes(*bytes))
+    }
+
+    pub(super) fn read_u32_be(&mut self) -> Result<u32, Error> {
+        let bytes = self.read_array()?;
+        Ok(u32::from_be_bytes(*bytes))
+    }
+
+    pub(super) fn read_double_be(&mut self) -> Result<f64, Error> {
+        let double_bytes = self.read_array()?;
+        Ok(f64::from_be_bytes(*double_bytes))
     }
 
     pub(super) fn read_string(&mut self) -> Result<&'de str, Error> {
         let header = self.read_u29()?;
+        self.read_string_with_header(header)
+    }
+
+    fn read_class_name(&mut self) -> Result<&'de str, Error> {
+        let header = self.read_u29()?;
+        if self.options.strict_class_names && header & 1 == 0 {
+            return Err(Error::ClassNameByReference);
+        }
+        self.read_string_with_header(header)
+    }
+
+    fn read_string_with_header(&mut self, header: u32) -> Result<&'de str, Error> {
         let value = (header >> 1) as usize;
         if header & 1 == 0 {
             // by reference
+            if self.options.disable_references {
+                return Err(Error::ReferencesDisabled);
+            }
             let string = *(self
                 .string_reference_table
                 .get(value)
                 .ok_or(Error::MissingStringReference)?);
             Ok(string)
-        } else if self.input.len() >= value {
+        } else {
             // by value
-            let slice = self.input.as_slice();
-            let string_bytes = unsafe { slice.get_unchecked(..value) };
-            let rest = unsafe { slice.get_unchecked(value..) };
-            self.input = rest.iter();
+            let string_bytes = self.read_bytes(value)?;
             let string = std::str::from_utf8(string_bytes)?;
             if !string.is_empty() {
                 self.string_reference_table.push(string);
             }
             Ok(string)
+        }
+    }
+
+    /// Reads `len` bytes that are not part of an AMF3 value, e.g. a length prefix
+    pub fn read_raw(&mut self, len: usize) -> Result<&'de [u8], crate::Error> {
+        Ok(self.read_bytes(len)?)
+    }
+
+    pub(super) fn read_bytes(&mut self, len: usize) -> Result<&'de [u8], Error> {
+        let slice = self.input.as_slice();
+        if slice.len() >= len {
+            let bytes = unsafe { slice.get_unchecked(..len) };
+            let rest = unsafe { slice.get_unchecked(len..) };
+            self.input = rest.iter();
+            Ok(bytes)
         } else {
-            Err(Error::EndOfStream)
+            Err(self.end_of_stream())
         }
     }
 
-    pub(crate) fn new(input: &'de [u8]) -> Self {
-        Self {
+    pub fn with_options(input: &'de [u8], options: DeserializerOptions) -> Self {
+        let input_limit = options.input_limit;
+        let mut deserializer = Self {
+            bytes: input,
             input: input.iter(),
+            limited: false,
             string_reference_table: Vec::new(),
+            trait_reference_table: Vec::new(),
+            object_reference_table: Vec::new(),
+            rewalk_budget: options.rewalk_budget,
+            options,
+        };
+        deserializer.set_input_limit(input_limit);
+        deserializer
+    }
+
+    /// Cuts off the input after `limit` bytes, see [`DeserializerOptions::input_limit`]
+    pub(super) fn set_input_limit(&mut self, limit: Option<usize>) {
+        self.options.input_limit = limit;
+        if let Some(limit) = limit.filter(|&limit| limit < self.bytes.len()) {
+            let position = self.position().min(limit);
+            self.bytes = &self.bytes[..limit];
+            self.input = self.bytes[position..].iter();
+            self.limited = true;
+        }
+    }
+
+    /// Reads the traits for an inline object `header` and returns their index in the traits table
+    pub(super) fn read_traits(&mut self, header: ObjectHeader) -> Result<usize, Error> {
+        if header.traits_ref {
+            if self.options.disable_references {
+                return Err(Error::ReferencesDisabled);
+            }
+            let index = header.count_or_index as usize;
+            if index < self.trait_reference_table.len() {
+                Ok(index)
+            } else {
+                Err(Error::MissingTraitReference)
+            }
+        } else {
+            let externalizable = header.externalizable;
+            if externalizable && (header.dynamic || header.count_or_index != 0) {
+                return Err(Error::InvalidTraits);
+            }
+            let class_name = self.read_class_name()?;
+            let mut members = Vec::new();
+            if !externalizable {
+                for _ in 0..self.check_count(header.count_or_index, 1)? {
+                    members.push(self.read_string()?);
+                }
+            }
+            self.trait_reference_table.push(Traits {
+                class_name,
+                members,
+                dynamic: header.dynamic,
+                externalizable,
+            });
+            Ok(self.trait_reference_table.len() - 1)
+        }
+    }
+
+    pub(super) fn traits(&self, index: usize) -> &Traits<'de> {
+        &self.trait_reference_table[index]
+    }
+
+    /// The offset of the next byte in the input
+    pub fn position(&self) -> usize {
+        self.bytes.len() - self.input.len()
+    }
+
+    /// Adds the complex value with its marker at `position` to the object table
+    pub(super) fn push_object_reference(&mut self, position: usize) -> usize {
+        self.object_reference_table.push(ObjectReference {
+            position,
+            complete: false,
+        });
+        self.object_reference_table.len() - 1
+    }
+
+    /// Marks the value at `index` as fully decoded, i.e. it may be referenced now
+    pub(super) fn complete_object_reference(&mut self, index: usize) {
+        if let Some(object) = self.object_reference_table.get_mut(index) {
+            object.complete = true;
+        }
+    }
+
+    /// Moves to the marker of the value at `index` in the object table
+    ///
+    /// All references that are added while decoding it again are removed on [`Self::restore`].
+    pub(super) fn seek_object_reference(&mut self, index: usize) -> Result<Checkpoint<'de>, Error> {
+        let object = self
+            .object_reference_table
+            .get(index)
+            .ok_or(Error::MissingObjectReference)?;
+        if !object.complete {
+            // a value that contains itself
+            return Err(Error::CyclicReference);
+        }
+        let position = object.position;
+        self.seek(position)
+    }
+
+    /// Moves to `position` to decode a value again, like [`Self::seek_object_reference`]
+    pub(super) fn seek(&mut self, position: usize) -> Result<Checkpoint<'de>, Error> {
+        self.rewalk_budget = self
+            .rewalk_budget
+            .checked_sub(1)
+            .ok_or(Error::WorkBudgetExceeded)?;
+        let checkpoint = Checkpoint {
+            input: self.input.clone(),
+            strings: self.string_reference_table.len(),
+            traits: self.trait_reference_table.len(),
+            objects: self.object_reference_table.len(),
+        };
+        self.input = self.bytes[position..].iter();
+        Ok(checkpoint)
+    }
+
+    pub(super) fn restore(&mut self, checkpoint: Checkpoint<'de>) {
+        self.input = checkpoint.input;
+        self.string_reference_table.truncate(checkpoint.strings);
+        self.trait_reference_table.truncate(checkpoint.traits);
+        self.object_reference_table.truncate(checkpoint.objects);
+    }
+
+    pub(super) fn into_reference_manifest(self) -> ReferenceManifest<'de> {
+        ReferenceManifest {
+            strings: self.string_reference_table,
+            traits: self
+                .trait_reference_table
+                .iter()
+                .map(|traits| traits.class_name)
+                .collect(),
+            objects: self
+                .object_reference_table
+                .iter()
+                .map(|object| object.position)
+                .collect(),
         }
     }
 
+    pub(crate) fn reset_reference_tables(&mut self) {
+        self.string_reference_table.clear();
+        self.trait_reference_table.clear();
+        self.object_reference_table.clear();
+    }
+
+    /// Whether `class_name` is an externalizable class with a single value as its body
+    pub(super) fn is_wrapper_class(&self, class_name: &str) -> bool {
+        (self.options.array_collection && class_name == super::ARRAY_COLLECTION)
+            || (self.options.object_proxy && class_name == super::OBJECT_PROXY)
+    }
+
+    /// Reads the header of a complex value that is skipped, and adds it to the object table
+    ///
+    /// Returns `None` for a reference, which has no body to skip.
+    fn read_skip_header(&mut self, position: usize) -> Result<Option<(u32, usize)>, Error> {
+        let header = self.read_u29()?;
+        if header & 1 == 0 {
+            if self.options.disable_references {
+                return Err(Error::ReferencesDisabled);
+            }
+            Ok(None)
+        } else {
+            Ok(Some((header, self.push_object_reference(position))))
+        }
+    }
+
+    /// Skips over the next value, keeping the reference tables in sync
     pub(crate) fn skip(&mut self) -> Result<(), Error> {
+        let position = self.position();
         let marker = self.read_marker()?;
         match marker {
+            Marker::Undefined | Marker::Null | Marker::False | Marker::True => {}
             Marker::Integer => {
                 self.read_u29()?;
             }
             Marker::Double => {
-                self.input = self
-                    .input
-                    .as_slice()
-                    .get(8..)
-                    .ok_or(Error::EndOfStream)?
-                    .iter();
-            }
-            Marker::String => todo!(),
-            Marker::XmlDoc => todo!(),
-            Marker::Date => todo!(),
-            Marker::Array => todo!(),
-            Marker::Object => todo!(),
-            Marker::Xml => todo!(),
-            Marker::ByteArray => todo!(),
-            Marker::VectorInt => todo!(),
-            Marker::VectorUInt => todo!(),
-            Marker::VectorDouble => todo!(),
-            Marker::VectorObject => todo!(),
-            Marker::Dictionary => todo!(),
-            _ => {}
+                self.read_double()?;
+            }
+            Marker::String => {
+                self.read_string()?;
+            }
+            Marker::XmlDoc | Marker::Xml | Marker::ByteArray => {
+                if let Some((header, index)) = self.read_skip_header(position)? {
+                    self.read_bytes((header >> 1) as usize)?;
+                    self.complete_object_reference(index);
+                }
+            }
+            Marker::Date => {
+                if let Some((_, index)) = self.read_skip_header(position)? {
+                    self.read_double()?;
+                    self.complete_object_reference(index);
+                }
+            }
+            Marker::Array => {
+                if let Some((header, index)) = self.read_skip_header(position)? {
+                    let count = self.check_count(header >> 1, 1)?;
+                    while !self.read_string()?.is_empty() {
+                        self.skip()?;
+                    }
+                    for _ in 0..count {
+                        self.skip()?;
+                    }
+                    self.complete_object_reference(index);
+                }
+            }
+            Marker::Object => {
+                if let Some((header, index)) = self.read_skip_header(position)? {
+                    let traits = self.read_traits(ObjectHeader::new(header))?;
+                    let traits = self.traits(traits);
+                    let (sealed, dynamic) = (traits.members.len(), traits.dynamic);
+                    if traits.externalizable {
+                        let class_name = traits.class_name;
+                        if !self.is_wrapper_class(class_name) {
+                            return Err(Error::Externalizable(class_name.to_owned()));
+                        }
+                        self.skip()?;
+                    } else {
+                        for _ in 0..sealed {
+                            self.skip()?;
+                        }
+                        if dynamic {
+                            while !self.read_string()?.is_empty() {
+                                self.skip()?;
+                            }
+                        }
+                    }
+                    self.complete_object_reference(index);
+                }
+            }
+            Marker::VectorInt | Marker::VectorUInt | Marker::VectorDouble => {
+                if let Some((header, index)) = self.read_skip_header(position)? {
+                    let item_size = if marker == Marker::VectorDouble { 8 } else { 4 };
+                    let count = self.check_count(header >> 1, item_size)?;
+                    let _fixed = self.read_flag()?;
+                    self.read_bytes(count * item_size)?;
+                    self.complete_object_reference(index);
+                }
+            }
+            Marker::VectorObject => {
+                if let Some((header, index)) = self.read_skip_header(position)? {
+                    let count = self.check_count(header >> 1, 1)?;
+                    let _fixed = self.read_flag()?;
+                    let _type_name = self.read_string()?;
+                    for _ in 0..count {
+                        self.skip()?;
+                    }
+                    self.complete_object_reference(index);
+                }
+            }
+            Marker::Dictionary => {
+                if let Some((header, index)) = self.read_skip_header(position)? {
+                    let len = self.check_count(header >> 1, 2)?;
+                    let _weak_keys = self.read_flag()?;
+                    for _ in 0..2 * len {
+                        self.skip()?;
+                    }
+                    self.complete_object_reference(index);
+                }
+            }
         }
         Ok(())
     }
 }
+
+pub struct Serializer {
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    pub(crate) fn new() -> Self {
+        Self { output: Vec::new() }
+    }
+
+    pub(crate) fn into_inner(self) -> Vec<u8> {
+        self.output
+    }
+
+    /// Writes to `output` from now on and returns the previous output
+    pub(super) fn replace_output(&mut self, output: Vec<u8>) -> Vec<u8> {
+        std::mem::replace(&mut self.output, output)
+    }
+
+    pub(super) fn write_byte(&mut self, byte: u8) {
+        self.output.push(byte);
+    }
+
+    pub(super) fn write_marker(&mut self, marker: Marker) {
+        self.write_byte(marker as u8);
+    }
+
+    /// Inverse of [`Deserializer::read_u29`]
+    pub(super) fn write_u29(&mut self, value: u32) -> Result<(), Error> {
+        if value < 0x80 {
+            self.write_byte(value as u8);
+        } else if value < 0x4000 {
+            self.write_byte((value >> 7) as u8 | 0x80);
+            self.write_byte(value as u8 & 0x7F);
+        } else if value < 0x200000 {
+            self.write_byte((value >> 14) as u8 | 0x80);
+            self.write_byte((value >> 7) as u8 | 0x80);
+            self.write_byte(value as u8 & 0x7F);
+        } else if value < 0x20000000 {
+            self.write_byte((value >> 22) as u8 | 0x80);
+            self.write_byte((value >> 15) as u8 | 0x80);
+            self.write_byte((value >> 8) as u8 | 0x80);
+            self.write_byte(value as u8);
+        } else {
+            return Err(Error::U29OutOfRange(value));
+        }
+        Ok(())
+    }
+
+    /// Writes the header of an inline value, i.e. `(len << 1) | 1`
+    pub(super) fn write_inline_header(&mut self, len: usize) -> Result<(), Error> {
+        let header = u32::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_mul(2))
+            .map_or(u32::MAX, |len| len | 1);
+        self.write_u29(header)
+    }
+
+    /// Writes the header of a value by reference, i.e. `index << 1`
+    pub(super) fn write_reference_header(&mut self, index: usize) -> Result<(), Error> {
+        let header = u32::try_from(index)
+            .ok()
+            .and_then(|index| index.checked_mul(2))
+            .unwrap_or(u32::MAX);
+        self.write_u29(header)
+    }
+
+    pub(super) fn write_double(&mut self, value: f64) {
+        self.output.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(super) fn write_bytes(&mut self, bytes: &[u8]) {
+        self.output.extend_from_slice(bytes);
+    }
+
+    pub(super) fn write_string(&mut self, value: &str) -> Result<(), Error> {
+        self.write_inline_header(value.len())?;
+        self.output.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Deserializer, Error, ObjectHeader, Serializer};
+
+    const U29_FORMS: [(u32, &[u8]); 8] = [
+        (0x00, &[0x00]),
+        (0x7F, &[0x7F]),
+        (0x80, &[0x81, 0x00]),
+        (0x3FFF, &[0xFF, 0x7F]),
+        (0x4000, &[0x81, 0x80, 0x00]),
+        (0x1FFFFF, &[0xFF, 0xFF, 0x7F]),
+        (0x200000, &[0x80, 0xC0, 0x80, 0x00]),
+        (0x1FFFFFFF, &[0xFF, 0xFF, 0xFF, 0xFF]),
+    ];
+
+    #[test]
+    fn test_read_u29() {
+        for (value, bytes) in U29_FORMS {
+            let mut deserializer = Deserializer::with_options(bytes, Default::default());
+            assert_eq!(deserializer.read_u29(), Ok(value));
+            assert!(deserializer.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_read_i29() {
+        for (bytes, value) in [
+            (&[0x7F][..], 0x7F),
+            (&[0xBF, 0xFF, 0xFF, 0xFF][..], 0x0FFFFFFF),
+            (&[0xC0, 0x80, 0x80, 0x00][..], -0x10000000),
+            (&[0xFF, 0xFF, 0xFF, 0xFF][..], -1),
+        ] {
+            let mut deserializer = Deserializer::with_options(bytes, Default::default());
+            assert_eq!(deserializer.read_i29(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_check_count() {
+        let deserializer = Deserializer::with_options(&[0; 16], Default::default());
+        assert_eq!(deserializer.check_count(2, 8), Ok(2));
+        assert_eq!(deserializer.check_count(3, 8), Err(Error::EndOfStream));
+        assert_eq!(
+            deserializer.check_count(0x1FFFFFFF, 8),
+            Err(Error::EndOfStream)
+        );
+        assert_eq!(
+            deserializer.check_count(0x1FFFFFFF, usize::MAX / 2),
+            Err(Error::LengthTooLarge)
+        );
+    }
+
+    #[test]
+    fn test_invalid_traits() {
+        for header in [0b1111, 0b10111] {
+            let mut deserializer = Deserializer::with_options(b"\x03A", Default::default());
+            assert_eq!(
+                deserializer.read_traits(ObjectHeader::new(header)),
+                Err(Error::InvalidTraits)
+            );
+        }
+        let mut deserializer = Deserializer::with_options(b"\x03A", Default::default());
+        assert_eq!(deserializer.read_traits(ObjectHeader::new(0b0111)), Ok(0));
+    }
+
+    #[test]
+    fn test_traits_reference() {
+        let mut deserializer =
+            Deserializer::with_options(b"\x0BPoint\x03x\x03y", Default::default());
+        assert_eq!(deserializer.read_traits(ObjectHeader::new(0x23)), Ok(0));
+        // the next object of the same class only refers to its traits
+        assert_eq!(deserializer.read_traits(ObjectHeader::new(0x01)), Ok(0));
+        let traits = deserializer.traits(0);
+        assert_eq!(traits.class_name, "Point");
+        assert_eq!(traits.members, ["x", "y"]);
+        assert!(!traits.dynamic && !traits.externalizable);
+        assert_eq!(
+            deserializer.read_traits(ObjectHeader::new(0x05)),
+            Err(Error::MissingTraitReference)
+        );
+        assert!(deserializer.is_empty());
+    }
+
+    #[test]
+    fn test_object_header() {
+        let header = |is_ref, traits_ref, externalizable, dynamic, count_or_index| ObjectHeader {
+            is_ref,
+            traits_ref,
+            externalizable,
+            dynamic,
+            count_or_index,
+        };
+        for (bytes, expected) in [
+            // object by reference
+            (&[0x04][..], header(true, false, false, false, 2)),
+            (&[0x82, 0x00][..], header(true, false, false, false, 0x80)),
+            // traits by reference
+            (&[0x01][..], header(false, true, false, false, 0)),
+            (&[0x0D][..], header(false, true, false, false, 3)),
+            // inline traits with sealed members
+            (&[0x03][..], header(false, false, false, false, 0)),
+            (&[0x23][..], header(false, false, false, false, 2)),
+            (&[0x81, 0x03][..], header(false, false, false, false, 8)),
+            // externalizable
+            (&[0x07][..], header(false, false, true, false, 0)),
+            // dynamic
+            (&[0x0B][..], header(false, false, false, true, 0)),
+            (&[0x1B][..], header(false, false, false, true, 1)),
+            // invalid, but decoded as written
+            (&[0x1F][..], header(false, false, true, true, 1)),
+        ] {
+            let mut deserializer = Deserializer::with_options(bytes, Default::default());
+            let value = deserializer.read_u29().unwrap();
+            assert_eq!(ObjectHeader::new(value), expected);
+        }
+    }
+
+    #[test]
+    fn test_write_u29() {
+        for (value, bytes) in U29_FORMS {
+            let mut serializer = Serializer::new();
+            assert_eq!(serializer.write_u29(value), Ok(()));
+            assert_eq!(serializer.into_inner(), bytes);
+        }
+        let mut serializer = Serializer::new();
+        assert_eq!(
+            serializer.write_u29(0x20000000),
+            Err(Error::U29OutOfRange(0x20000000))
+        );
+        assert_eq!(
+            serializer.write_inline_header(0x10000000),
+            Err(Error::U29OutOfRange(0x20000001))
+        );
+        assert!(serializer.into_inner().is_empty());
+    }
+}
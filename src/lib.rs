@@ -1,36 +1,126 @@
 use std::fmt;
 
-use format::Marker;
+use format::ObjectHeader;
 use serde::{de::value::BorrowedStrDeserializer, forward_to_deserialize_any, Deserialize};
-use traits::{VisitDouble, VisitInt};
+use traits::{IntegralDouble, VisitDouble, VisitInt};
 
+pub mod amf0;
+pub mod amf_packet;
+pub mod date;
+pub mod duration_millis;
+#[cfg(feature = "flex")]
+pub mod flex;
 mod format;
+pub mod options;
+mod ser;
 mod traits;
+#[cfg(feature = "json")]
+mod transcode;
+#[cfg(feature = "uuid")]
+pub mod uuid;
+pub mod value;
 
-#[derive(Debug, PartialEq)]
+pub use format::{Deserializer as FormatDeserializer, Marker, ReferenceManifest};
+pub use options::{DeserializerOptions, Endianness, NumberHint, UnknownMarkerHandler};
+pub use ser::{serialize, ByteSerializer};
+pub use value::{Value, ValueRef};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum ErrorKind {
-    #[allow(dead_code)]
     Unimplemented,
     Custom(String),
     Format(format::Error),
+    Externalizable(String),
+    KeyMustBeAString,
+    UnexpectedMarker {
+        expected: Marker,
+        found: Marker,
+    },
+    NonIntegralDouble,
+    InexactDouble,
+    IntegerRange(i32),
+    DoubleRange,
+    NonFiniteDouble,
+    #[cfg(feature = "json")]
+    Json(JsonError),
+}
+
+/// A [`serde_json::Error`] from writing JSON, shared so that [`Error`] stays `Clone`
+#[cfg(feature = "json")]
+#[derive(Debug, Clone)]
+struct JsonError(std::sync::Arc<serde_json::Error>);
+
+#[cfg(feature = "json")]
+impl PartialEq for JsonError {
+    fn eq(&self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&self.0, &other.0)
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[cfg(feature = "json")]
+impl Eq for JsonError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Error {
     kind: ErrorKind,
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            #[cfg(feature = "json")]
+            ErrorKind::Json(e) => Some(&*e.0),
+            _ => None,
+        }
+    }
+}
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.kind {
             ErrorKind::Unimplemented => write!(f, "Unimplemented"),
             ErrorKind::Custom(msg) => write!(f, "Custom: {}", msg),
             ErrorKind::Format(fmt) => write!(f, "Format error: {:?}", fmt),
+            ErrorKind::Externalizable(class_name) => {
+                write!(f, "Unsupported externalizable class: {}", class_name)
+            }
+            ErrorKind::KeyMustBeAString => write!(f, "Key must be a non-empty string"),
+            ErrorKind::UnexpectedMarker { expected, found } => {
+                write!(f, "Expected marker {:?}, found {:?}", expected, found)
+            }
+            ErrorKind::NonIntegralDouble => {
+                write!(f, "Double with a fractional part for an integer")
+            }
+            ErrorKind::InexactDouble => {
+                write!(f, "Double too large to be an exact integer")
+            }
+            ErrorKind::IntegerRange(value) => {
+                write!(f, "Integer {} out of range for the requested type", value)
+            }
+            ErrorKind::DoubleRange => {
+                write!(f, "Double out of range for the requested type")
+            }
+            ErrorKind::NonFiniteDouble => write!(f, "Double is NaN or infinite"),
+            #[cfg(feature = "json")]
+            ErrorKind::Json(e) => write!(f, "JSON error: {}", e.0),
         }
     }
 }
 
+impl Error {
+    /// The byte where a marker was expected, if it is not a valid AMF3 marker
+    pub fn invalid_marker_byte(&self) -> Option<u8> {
+        match self.kind {
+            ErrorKind::Format(format::Error::InvalidMarker(byte)) => Some(byte),
+            _ => None,
+        }
+    }
+
+    /// Whether decoding stopped at [`DeserializerOptions::input_limit`]
+    pub fn is_input_limit_exceeded(&self) -> bool {
+        self.kind == ErrorKind::Format(format::Error::InputLimitExceeded)
+    }
+}
+
 impl serde::de::Error for Error {
     fn custom<T>(msg: T) -> Self
     where
@@ -42,6 +132,17 @@ impl serde::de::Error for Error {
     }
 }
 
+impl serde::ser::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        Self {
+            kind: ErrorKind::Custom(msg.to_string()),
+        }
+    }
+}
+
 struct ByteDeserializerSeq<'a, 'de> {
     len: usize,
     inner: &'a mut ByteDeserializer<'de>,
@@ -61,9 +162,109 @@ impl<'a, 'de> serde::de::SeqAccess<'de> for ByteDeserializerSeq<'a, 'de> {
             Ok(None)
         }
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+/// The entries of a dictionary, each as a sequence of a key and a value
+struct ByteDeserializerPairs<'a, 'de> {
+    len: usize,
+    inner: &'a mut ByteDeserializer<'de>,
+}
+
+impl<'a, 'de> serde::de::SeqAccess<'de> for ByteDeserializerPairs<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        if self.len > 0 {
+            self.len -= 1;
+            let pair = ByteDeserializerSeq {
+                len: 2,
+                inner: &mut *self.inner,
+            };
+            seed.deserialize(serde::de::value::SeqAccessDeserializer::new(pair))
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+/// Passes the weak keys flag of a dictionary to a [`value::Dictionary`],
+/// as the variant of an enum whose content is the sequence of entries
+struct ByteDeserializerWeakKeys<'a, 'de> {
+    weak_keys: bool,
+    len: usize,
+    inner: &'a mut ByteDeserializer<'de>,
+}
+
+impl<'a, 'de> serde::de::EnumAccess<'de> for ByteDeserializerWeakKeys<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<T>(self, seed: T) -> Result<(T::Value, Self::Variant), Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        let deserializer = serde::de::value::BoolDeserializer::<Error>::new(self.weak_keys);
+        Ok((seed.deserialize(deserializer)?, self))
+    }
+}
+
+impl<'a, 'de> serde::de::VariantAccess<'de> for ByteDeserializerWeakKeys<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(Error {
+            kind: ErrorKind::Unimplemented,
+        })
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        Err(Error {
+            kind: ErrorKind::Unimplemented,
+        })
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_seq(ByteDeserializerPairs {
+            len: self.len,
+            inner: self.inner,
+        })
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(Error {
+            kind: ErrorKind::Unimplemented,
+        })
+    }
 }
 
 struct ByteDeserializerMap<'a, 'de> {
+    /// The index of the next dense item
+    index: usize,
     len: usize,
     next_key: &'de str,
     inner: &'a mut ByteDeserializer<'de>,
@@ -76,17 +277,20 @@ impl<'a, 'de> serde::de::MapAccess<'de> for ByteDeserializerMap<'a, 'de> {
     where
         K: serde::de::DeserializeSeed<'de>,
     {
+        while !self.next_key.is_empty() && self.inner.skip_undefined()? {
+            self.next_key = self.inner.inner.read_string()?;
+        }
         if self.next_key.is_empty() {
             if self.len > 0 {
                 self.len -= 1;
-                let deserializer = serde::de::value::UsizeDeserializer::new(self.len);
+                self.index += 1;
+                let deserializer = serde::de::value::UsizeDeserializer::new(self.index - 1);
                 seed.deserialize(deserializer).map(Some)
             } else {
                 Ok(None)
             }
         } else {
-            let deserializer = BorrowedStrDeserializer::new(self.next_key);
-            seed.deserialize(deserializer).map(Some)
+            seed.deserialize(MemberName(self.next_key)).map(Some)
         }
     }
 
@@ -102,325 +306,2831 @@ impl<'a, 'de> serde::de::MapAccess<'de> for ByteDeserializerMap<'a, 'de> {
     }
 }
 
-pub struct ByteDeserializer<'de> {
-    inner: format::Deserializer<'de>,
+struct ByteDeserializerObject<'a, 'de> {
+    traits: usize,
+    sealed: usize,
+    inner: &'a mut ByteDeserializer<'de>,
 }
 
-impl<'de> ByteDeserializer<'de> {
-    pub fn from_bytes(input: &'de [u8]) -> Self {
-        Self {
-            inner: format::Deserializer::new(input),
-        }
-    }
+impl<'a, 'de> serde::de::MapAccess<'de> for ByteDeserializerObject<'a, 'de> {
+    type Error = Error;
 
-    fn deserialize_array<V: serde::de::Visitor<'de>>(
-        &mut self,
-        visitor: V,
-    ) -> Result<V::Value, Error> {
-        let header = self.inner.read_u29()?;
-        let value = (header >> 1) as usize;
-        if header & 1 == 0 {
-            // array by reference
-            unimplemented!()
-        } else {
-            // dense count
-            let first_key = self.inner.read_string()?;
-            if first_key.is_empty() {
-                // only dense keys => array
-                visitor.visit_seq(ByteDeserializerSeq {
-                    inner: self,
-                    len: value,
-                })
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        loop {
+            let traits = self.inner.inner.traits(self.traits);
+            let key = if let Some(&member) = traits.members.get(self.sealed) {
+                self.sealed += 1;
+                member
+            } else if traits.dynamic {
+                self.inner.inner.read_string()?
             } else {
-                visitor.visit_map(ByteDeserializerMap {
-                    inner: self,
-                    len: value,
-                    next_key: first_key,
-                })
+                ""
+            };
+            if key.is_empty() {
+                return Ok(None);
+            } else if !self.inner.skip_undefined()? {
+                return seed.deserialize(MemberName(key)).map(Some);
             }
         }
     }
 
-    fn deserialize_into<V, N: VisitInt, F: VisitDouble>(
-        &mut self,
-        visitor: V,
-    ) -> Result<V::Value, Error>
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
     where
-        V: serde::de::Visitor<'de>,
+        V: serde::de::DeserializeSeed<'de>,
     {
-        let marker = self.inner.read_marker()?;
-        match marker {
-            Marker::Undefined => visitor.visit_none(),
-            Marker::Null => visitor.visit_none(),
-            Marker::False => visitor.visit_bool(false),
-            Marker::True => visitor.visit_bool(true),
-            Marker::Integer => N::visit_int(visitor, self.inner.read_u29()?),
-            Marker::Double => F::visit_double(visitor, self.inner.read_double()?),
-            Marker::String => visitor.visit_borrowed_str(self.inner.read_string()?),
-            Marker::XmlDoc => todo!(),
-            Marker::Date => todo!(),
-            Marker::Array => self.deserialize_array(visitor),
-            Marker::Object => todo!(),
-            Marker::Xml => todo!(),
-            Marker::ByteArray => todo!(),
-            Marker::VectorInt => todo!(),
-            Marker::VectorUInt => todo!(),
-            Marker::VectorDouble => todo!(),
-            Marker::VectorObject => todo!(),
-            Marker::Dictionary => todo!(),
+        seed.deserialize(&mut *self.inner)
+    }
+}
+
+enum EntriesStart<'de> {
+    Array { len: usize, first_key: &'de str },
+    Object(usize),
+}
+
+enum EntriesAccess<'a, 'de> {
+    Array(ByteDeserializerMap<'a, 'de>),
+    Object(ByteDeserializerObject<'a, 'de>),
+}
+
+/// The entries of an `Array` or `Object`, decoded one at a time, see [`ByteDeserializer::entries`]
+///
+/// Associative keys come before the dense items of an array, which use their index as
+/// the key. Sealed members come before the dynamic members of an object.
+pub struct Entries<'a, 'de> {
+    access: EntriesAccess<'a, 'de>,
+    /// The values in the object table that are complete after the last entry
+    objects: Vec<usize>,
+    /// Where to continue after a value that was decoded by reference
+    checkpoint: Option<(format::Checkpoint<'de>, Option<DecodeStats>)>,
+    done: bool,
+}
+
+impl<'a, 'de> Entries<'a, 'de> {
+    fn inner(&mut self) -> &mut ByteDeserializer<'de> {
+        match &mut self.access {
+            EntriesAccess::Array(map) => map.inner,
+            EntriesAccess::Object(object) => object.inner,
+        }
+    }
+
+    fn finish(&mut self) {
+        self.done = true;
+        for index in std::mem::take(&mut self.objects) {
+            self.inner().inner.complete_object_reference(index);
+        }
+        if let Some((checkpoint, stats)) = self.checkpoint.take() {
+            let inner = self.inner();
+            inner.stats = stats;
+            inner.inner.restore(checkpoint);
         }
     }
 }
 
-impl From<format::Error> for Error {
-    fn from(e: format::Error) -> Self {
-        Self {
-            kind: ErrorKind::Format(e),
+impl<'a, 'de> Iterator for Entries<'a, 'de> {
+    type Item = Result<(String, Value), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use serde::de::MapAccess;
+
+        if self.done {
+            return None;
+        }
+        let entry = match &mut self.access {
+            EntriesAccess::Array(map) => map.next_entry::<value::Key, Value>(),
+            EntriesAccess::Object(object) => object.next_entry::<value::Key, Value>(),
+        };
+        match entry {
+            Ok(Some((key, value))) => Some(Ok((key.0.into_owned(), value))),
+            Ok(None) => {
+                self.finish();
+                None
+            }
+            Err(e) => {
+                self.finish();
+                Some(Err(e))
+            }
         }
     }
 }
 
-pub fn deserialize<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<T, Error> {
-    let mut deserializer = ByteDeserializer::from_bytes(input);
-    T::deserialize(&mut deserializer)
+impl<'a, 'de> Drop for Entries<'a, 'de> {
+    fn drop(&mut self) {
+        use serde::de::{IgnoredAny, MapAccess};
+
+        if self.done {
+            return;
+        }
+        // skip the entries that were not read, so the next value starts after them
+        loop {
+            let entry = match &mut self.access {
+                EntriesAccess::Array(map) => map.next_entry::<IgnoredAny, IgnoredAny>(),
+                EntriesAccess::Object(object) => object.next_entry::<IgnoredAny, IgnoredAny>(),
+            };
+            if !matches!(entry, Ok(Some(_))) {
+                break;
+            }
+        }
+        self.finish();
+    }
 }
 
-impl<'de> serde::Deserializer<'de> for &mut ByteDeserializer<'de> {
-    type Error = Error;
+/// The name of a dynamic member or associative key, which is parsed for integer keys
+///
+/// Flash writes sparse arrays as objects with members like `"0"` and `"1"`.
+struct MemberName<'de>(&'de str);
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: serde::de::Visitor<'de>,
+            {
+                match self.0.parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(serde::de::Error::invalid_value(
+                        serde::de::Unexpected::Str(self.0),
+                        &visitor,
+                    )),
+                }
+            }
+        )*
+    };
+}
 
-    forward_to_deserialize_any! { bool str string option unit seq tuple map struct identifier }
+impl<'de> serde::Deserializer<'de> for MemberName<'de> {
+    type Error = Error;
 
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_into::<V, u32, f64>(visitor)
+    forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf option unit unit_struct newtype_struct
+        seq tuple tuple_struct map struct identifier ignored_any
     }
 
-    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_into::<V, i8, i8>(visitor)
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
     }
 
-    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        self.deserialize_into::<V, i16, i16>(visitor)
+        visitor.visit_borrowed_str(self.0)
     }
 
-    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        self.deserialize_into::<V, i32, i32>(visitor)
+        visitor.visit_enum(BorrowedStrDeserializer::new(self.0))
     }
+}
 
-    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+/// Tells a [`Value`] which marker the next value has, as the variant of an enum
+/// whose content is the whole value
+struct ByteDeserializerMarker<'a, 'de> {
+    inner: &'a mut ByteDeserializer<'de>,
+}
+
+impl<'a, 'de> serde::de::EnumAccess<'de> for ByteDeserializerMarker<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<T>(self, seed: T) -> Result<(T::Value, Self::Variant), Self::Error>
     where
-        V: serde::de::Visitor<'de>,
+        T: serde::de::DeserializeSeed<'de>,
     {
-        self.deserialize_into::<V, i64, i64>(visitor)
+        let marker = self.inner.inner.peek_marker()?;
+        let deserializer = serde::de::value::U8Deserializer::<Error>::new(marker as u8);
+        Ok((seed.deserialize(deserializer)?, self))
     }
+}
 
-    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_into::<V, u8, u8>(visitor)
+impl<'a, 'de> serde::de::VariantAccess<'de> for ByteDeserializerMarker<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(Error {
+            kind: ErrorKind::Unimplemented,
+        })
     }
 
-    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
     where
-        V: serde::de::Visitor<'de>,
+        T: serde::de::DeserializeSeed<'de>,
     {
-        self.deserialize_into::<V, u16, u16>(visitor)
+        seed.deserialize(&mut *self.inner)
     }
 
-    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        self.deserialize_into::<V, u32, u32>(visitor)
+        Err(Error {
+            kind: ErrorKind::Unimplemented,
+        })
     }
 
-    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        self.deserialize_into::<V, u64, u64>(visitor)
+        Err(Error {
+            kind: ErrorKind::Unimplemented,
+        })
     }
+}
 
-    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+/// The `u29` header of a value that is part of the object reference table
+enum Header {
+    /// The value follows inline, and has `index` in the object reference table
+    Inline { header: u32, index: usize },
+    /// The value is at this index in the object reference table
+    Reference(usize),
+}
+
+/// The header shared by the four `Vector` types
+#[derive(Debug, PartialEq, Eq)]
+enum VectorHeader {
+    /// The elements follow inline
+    Inline {
+        index: usize,
+        count: usize,
+        fixed: bool,
+    },
+    /// The vector is at this index in the object reference table
+    Reference(usize),
+}
+
+struct ByteDeserializerVector<'a, 'de> {
+    marker: Marker,
+    len: usize,
+    inner: &'a mut ByteDeserializer<'de>,
+}
+
+impl<'a, 'de> serde::de::SeqAccess<'de> for ByteDeserializerVector<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
     where
-        V: serde::de::Visitor<'de>,
+        T: serde::de::DeserializeSeed<'de>,
     {
-        self.deserialize_into::<V, f32, f32>(visitor)
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        let format = &mut self.inner.inner;
+        match self.marker {
+            Marker::VectorInt => {
+                let value = format.read_u32()? as i32;
+                seed.deserialize(serde::de::value::I32Deserializer::new(value))
+            }
+            Marker::VectorUInt => {
+                let value = format.read_u32()?;
+                seed.deserialize(serde::de::value::U32Deserializer::new(value))
+            }
+            Marker::VectorDouble => {
+                let value = format.read_double()?;
+                seed.deserialize(serde::de::value::F64Deserializer::new(value))
+            }
+            _ => seed.deserialize(&mut *self.inner),
+        }
+        .map(Some)
     }
 
-    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_into::<V, f64, f64>(visitor)
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
     }
+}
+
+struct ByteDeserializerDictionary<'a, 'de> {
+    len: usize,
+    inner: &'a mut ByteDeserializer<'de>,
+}
+
+impl<'a, 'de> serde::de::MapAccess<'de> for ByteDeserializerDictionary<'a, 'de> {
+    type Error = Error;
 
-    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
     where
-        V: serde::de::Visitor<'de>,
+        K: serde::de::DeserializeSeed<'de>,
     {
-        todo!()
+        if self.len > 0 {
+            self.len -= 1;
+            seed.deserialize(&mut *self.inner).map(Some)
+        } else {
+            Ok(None)
+        }
     }
 
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
     where
-        V: serde::de::Visitor<'de>,
+        V: serde::de::DeserializeSeed<'de>,
     {
-        todo!()
+        seed.deserialize(&mut *self.inner)
     }
 
-    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        todo!()
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
     }
+}
 
-    fn deserialize_unit_struct<V>(
-        self,
-        _name: &'static str,
-        _visitor: V,
-    ) -> Result<V::Value, Self::Error>
+/// The largest integer `n` for which `n + 1` is also an exact double, i.e. `2^53 - 1`
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// Externalizable class that wraps a single value in a Flex application
+const ARRAY_COLLECTION: &str = "flex.messaging.io.ArrayCollection";
+/// Externalizable class that wraps a single object in a Flex application
+const OBJECT_PROXY: &str = "flex.messaging.io.ObjectProxy";
+
+/// The number of values that were decoded for each marker
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DecodeStats {
+    counts: [usize; Marker::Dictionary as usize + 1],
+}
+
+impl DecodeStats {
+    /// Values with this marker, including references to earlier values
+    pub fn count(&self, marker: Marker) -> usize {
+        self.counts[marker as usize]
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+/// A deserializer for AMF3 values borrowed from a byte slice
+///
+/// Byte arrays are handed out as borrowed bytes, so decoding one into `&[u8]`
+/// never allocates for its content. Owned targets see the exact length up front: a single copy
+/// for byte buffers, and an exact size hint when decoding into a `Vec<u8>`.
+pub struct ByteDeserializer<'de> {
+    inner: format::Deserializer<'de>,
+    stats: Option<DecodeStats>,
+}
+
+impl<'de> ByteDeserializer<'de> {
+    pub fn from_bytes(input: &'de [u8]) -> Self {
+        Self::with_options(input, DeserializerOptions::default())
+    }
+
+    pub fn with_options(input: &'de [u8], options: DeserializerOptions) -> Self {
+        Self {
+            inner: format::Deserializer::with_options(input, options),
+            stats: None,
+        }
+    }
+
+    /// Continue decoding at the position and with the reference tables of `inner`
+    pub fn from_format(inner: FormatDeserializer<'de>) -> Self {
+        Self { inner, stats: None }
+    }
+
+    /// The position and reference tables, e.g. to read bytes between two values
+    pub fn into_format(self) -> FormatDeserializer<'de> {
+        self.inner
+    }
+
+    /// Count the decoded values per marker, see [`Self::into_stats`]
+    pub fn with_stats(mut self) -> Self {
+        self.stats = Some(DecodeStats::default());
+        self
+    }
+
+    /// The strings, traits and objects that later values could have referred to,
+    /// e.g. to see how a payload uses references
+    pub fn into_reference_manifest(self) -> ReferenceManifest<'de> {
+        self.inner.into_reference_manifest()
+    }
+
+    /// The counts since [`Self::with_stats`], if enabled
+    pub fn into_stats(self) -> Option<DecodeStats> {
+        self.stats
+    }
+
+    fn read_marker(&mut self) -> Result<Marker, Error> {
+        let marker = self.inner.read_marker()?;
+        if let Some(stats) = &mut self.stats {
+            stats.counts[marker as usize] += 1;
+        }
+        Ok(marker)
+    }
+
+    /// Check the marker of the next value without consuming it
+    pub fn expect_marker(&mut self, expected: Marker) -> Result<(), Error> {
+        let found = self.inner.peek_marker()?;
+        if found == expected {
+            Ok(())
+        } else {
+            Err(Error {
+                kind: ErrorKind::UnexpectedMarker { expected, found },
+            })
+        }
+    }
+
+    /// Read a [`Marker::Double`] and its value, but nothing else
+    pub fn expect_double(&mut self) -> Result<f64, Error> {
+        self.expect_marker(Marker::Double)?;
+        self.read_marker()?;
+        Ok(self.inner.read_double()?)
+    }
+
+    /// Forget all strings, traits and objects decoded so far, keeping the position
+    ///
+    /// Each body of an AMF packet starts with empty reference tables.
+    pub fn clear_reference_tables(&mut self) {
+        self.inner.reset_reference_tables();
+    }
+
+    /// The offset of the next byte in the input, e.g. to locate a decode error
+    pub fn position(&self) -> usize {
+        self.inner.position()
+    }
+
+    /// See [`DeserializerOptions::number_hint`]
+    pub fn number_hint(mut self, hint: NumberHint) -> Self {
+        self.inner.options.number_hint = hint;
+        self
+    }
+
+    /// See [`DeserializerOptions::integral_doubles`]
+    pub fn integral_doubles(mut self, enabled: bool) -> Self {
+        self.inner.options.integral_doubles = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::strict_integers`]
+    pub fn strict_integers(mut self, enabled: bool) -> Self {
+        self.inner.options.strict_integers = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::char_code_points`]
+    pub fn char_code_points(mut self, enabled: bool) -> Self {
+        self.inner.options.char_code_points = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::undefined_as_absent`]
+    pub fn undefined_as_absent(mut self, enabled: bool) -> Self {
+        self.inner.options.undefined_as_absent = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::strict_casts`]
+    pub fn strict_casts(mut self, enabled: bool) -> Self {
+        self.inner.options.strict_casts = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::array_collection`]
+    pub fn array_collection(mut self, enabled: bool) -> Self {
+        self.inner.options.array_collection = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::object_proxy`]
+    pub fn object_proxy(mut self, enabled: bool) -> Self {
+        self.inner.options.object_proxy = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::unwrap_single_element`]
+    pub fn unwrap_single_element(mut self, enabled: bool) -> Self {
+        self.inner.options.unwrap_single_element = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::strict_class_names`]
+    pub fn strict_class_names(mut self, enabled: bool) -> Self {
+        self.inner.options.strict_class_names = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::disable_references`]
+    pub fn disable_references(mut self, enabled: bool) -> Self {
+        self.inner.options.disable_references = enabled;
+        self
+    }
+
+    /// See [`DeserializerOptions::rewalk_budget`]
+    pub fn rewalk_budget(mut self, budget: usize) -> Self {
+        self.inner.options.rewalk_budget = budget;
+        self.inner.rewalk_budget = budget;
+        self
+    }
+
+    /// See [`DeserializerOptions::double_endianness`]
+    pub fn double_endianness(mut self, endianness: Endianness) -> Self {
+        self.inner.options.double_endianness = endianness;
+        self
+    }
+
+    /// See [`DeserializerOptions::input_limit`]
+    pub fn input_limit(mut self, limit: Option<usize>) -> Self {
+        self.inner.set_input_limit(limit);
+        self
+    }
+
+    /// See [`DeserializerOptions::unknown_markers`]
+    pub fn unknown_markers(mut self, handler: Option<UnknownMarkerHandler>) -> Self {
+        self.inner.options.unknown_markers = handler;
+        self
+    }
+
+    /// Decode the entries of the next `Array` or `Object` lazily, instead of all at once
+    ///
+    /// Entries that are not read are skipped when the iterator is dropped.
+    pub fn entries(&mut self) -> Result<Entries<'_, 'de>, Error> {
+        let mut objects = Vec::new();
+        let mut checkpoint = None;
+        let start = self.start_entries(&mut objects, &mut checkpoint);
+        let access = match start {
+            Ok(EntriesStart::Array { len, first_key }) => {
+                EntriesAccess::Array(ByteDeserializerMap {
+                    index: 0,
+                    len,
+                    next_key: first_key,
+                    inner: self,
+                })
+            }
+            Ok(EntriesStart::Object(traits)) => EntriesAccess::Object(ByteDeserializerObject {
+                traits,
+                sealed: 0,
+                inner: self,
+            }),
+            Err(e) => {
+                if let Some((checkpoint, stats)) = checkpoint {
+                    self.stats = stats;
+                    self.inner.restore(checkpoint);
+                }
+                return Err(e);
+            }
+        };
+        Ok(Entries {
+            access,
+            objects,
+            checkpoint,
+            done: false,
+        })
+    }
+
+    /// Reads up to the first entry of an `Array` or `Object`, following references and wrappers
+    fn start_entries(
+        &mut self,
+        objects: &mut Vec<usize>,
+        checkpoint: &mut Option<(format::Checkpoint<'de>, Option<DecodeStats>)>,
+    ) -> Result<EntriesStart<'de>, Error> {
+        loop {
+            let marker = self.read_marker()?;
+            let header = match self.read_header()? {
+                Header::Reference(index) => {
+                    let seek = self.inner.seek_object_reference(index)?;
+                    if checkpoint.is_none() {
+                        // the referenced value has been counted already
+                        *checkpoint = Some((seek, self.stats.take()));
+                    }
+                    continue;
+                }
+                Header::Inline { header, index } => {
+                    objects.push(index);
+                    header
+                }
+            };
+            match marker {
+                Marker::Array => {
+                    let len = self.inner.check_count(header >> 1, 1)?;
+                    let first_key = self.inner.read_string()?;
+                    return Ok(EntriesStart::Array { len, first_key });
+                }
+                Marker::Object => {
+                    let traits = self.inner.read_traits(ObjectHeader::new(header))?;
+                    let traits_ref = self.inner.traits(traits);
+                    if !traits_ref.externalizable {
+                        return Ok(EntriesStart::Object(traits));
+                    }
+                    let class_name = traits_ref.class_name;
+                    if !self.inner.is_wrapper_class(class_name) {
+                        return Err(Error {
+                            kind: ErrorKind::Externalizable(class_name.to_owned()),
+                        });
+                    }
+                    // continue with the wrapped value
+                }
+                found => {
+                    return Err(Error {
+                        kind: ErrorKind::UnexpectedMarker {
+                            expected: Marker::Object,
+                            found,
+                        },
+                    })
+                }
+            }
+        }
+    }
+
+    /// Consumes the next value if it is `Undefined` and those are treated as absent
+    fn skip_undefined(&mut self) -> Result<bool, Error> {
+        if self.inner.options.undefined_as_absent && self.inner.peek_marker()? == Marker::Undefined
+        {
+            self.read_marker()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Reads the header of a value that is part of the object reference table
+    fn read_header(&mut self) -> Result<Header, Error> {
+        // the marker has just been read
+        let position = self.inner.position() - 1;
+        let header = self.inner.read_u29()?;
+        if header & 1 == 0 {
+            if self.inner.options.disable_references {
+                return Err(format::Error::ReferencesDisabled.into());
+            }
+            Ok(Header::Reference((header >> 1) as usize))
+        } else {
+            let index = self.inner.push_object_reference(position);
+            Ok(Header::Inline { header, index })
+        }
+    }
+
+    /// Decodes the value at `index` in the object reference table again
+    fn deserialize_reference<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        index: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        todo!()
+        let checkpoint = self.inner.seek_object_reference(index)?;
+        // the reference itself has been counted already
+        let stats = self.stats.take();
+        let result = self.deserialize_into::<V, N, F>(visitor);
+        self.stats = stats;
+        self.inner.restore(checkpoint);
+        result
+    }
+
+    fn deserialize_object<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let (header, index) = match self.read_header()? {
+            Header::Reference(index) => {
+                return self.deserialize_reference::<V, N, F>(index, visitor)
+            }
+            Header::Inline { header, index } => (header, index),
+        };
+        let traits = self.inner.read_traits(ObjectHeader::new(header))?;
+        let traits_ref = self.inner.traits(traits);
+        let result = if traits_ref.externalizable {
+            let class_name = traits_ref.class_name;
+            if self.inner.is_wrapper_class(class_name) {
+                // the body is a single value
+                self.deserialize_into::<V, N, F>(visitor)
+            } else {
+                Err(Error {
+                    kind: ErrorKind::Externalizable(class_name.to_owned()),
+                })
+            }
+        } else {
+            visitor.visit_map(ByteDeserializerObject {
+                traits,
+                sealed: 0,
+                inner: self,
+            })
+        };
+        self.inner.complete_object_reference(index);
+        result
+    }
+
+    fn deserialize_array<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let (header, index) = match self.read_header()? {
+            Header::Reference(index) => {
+                return self.deserialize_reference::<V, N, F>(index, visitor)
+            }
+            Header::Inline { header, index } => (header, index),
+        };
+        // dense count
+        let value = self.inner.check_count(header >> 1, 1)?;
+        let first_key = self.inner.read_string()?;
+        let result = if first_key.is_empty() {
+            // only dense keys => array
+            let mut seq = ByteDeserializerSeq {
+                inner: self,
+                len: value,
+            };
+            visitor.visit_seq(&mut seq).and_then(|v| match seq.len {
+                0 => Ok(v),
+                _ => Err(serde::de::Error::invalid_length(
+                    value,
+                    &"no more elements than the target",
+                )),
+            })
+        } else {
+            visitor.visit_map(ByteDeserializerMap {
+                inner: self,
+                index: 0,
+                len: value,
+                next_key: first_key,
+            })
+        };
+        self.inner.complete_object_reference(index);
+        result
+    }
+
+    /// Reads the count and fixed flag of a vector, after the marker
+    fn read_vector_header(&mut self) -> Result<VectorHeader, Error> {
+        match self.read_header()? {
+            Header::Reference(index) => Ok(VectorHeader::Reference(index)),
+            Header::Inline { header, index } => {
+                let count = self.inner.check_count(header >> 1, 1)?;
+                let fixed = self.inner.read_flag()?;
+                Ok(VectorHeader::Inline {
+                    index,
+                    count,
+                    fixed,
+                })
+            }
+        }
+    }
+
+    fn deserialize_vector<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        marker: Marker,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.read_vector_header()? {
+            VectorHeader::Reference(index) => self.deserialize_reference::<V, N, F>(index, visitor),
+            VectorHeader::Inline { index, count, .. } => {
+                let item_size = match marker {
+                    Marker::VectorInt | Marker::VectorUInt => 4,
+                    Marker::VectorDouble => 8,
+                    _ => 1,
+                };
+                self.inner.check_count(count as u32, item_size)?;
+                if marker == Marker::VectorObject {
+                    let _type_name = self.inner.read_string()?;
+                }
+                let mut seq = ByteDeserializerVector {
+                    marker,
+                    len: count,
+                    inner: self,
+                };
+                let result = visitor.visit_seq(&mut seq).and_then(|value| {
+                    // e.g. a `[T; N]` that is shorter than the vector
+                    match seq.len {
+                        0 => Ok(value),
+                        _ => Err(serde::de::Error::invalid_length(
+                            count,
+                            &"no more elements than the target",
+                        )),
+                    }
+                });
+                self.inner.complete_object_reference(index);
+                result
+            }
+        }
+    }
+
+    fn deserialize_xml<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.read_header()? {
+            Header::Reference(index) => self.deserialize_reference::<V, N, F>(index, visitor),
+            Header::Inline { header, index } => {
+                let len = self.inner.check_count(header >> 1, 1)?;
+                let bytes = self.inner.read_bytes(len)?;
+                let string = std::str::from_utf8(bytes).map_err(format::Error::from)?;
+                self.inner.complete_object_reference(index);
+                visitor.visit_borrowed_str(string)
+            }
+        }
+    }
+
+    fn deserialize_dictionary<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.read_header()? {
+            Header::Reference(index) => self.deserialize_reference::<V, N, F>(index, visitor),
+            Header::Inline { header, index } => {
+                // a key and a value
+                let len = self.inner.check_count(header >> 1, 2)?;
+                let _weak_keys = self.inner.read_flag()?;
+                let result = visitor.visit_map(ByteDeserializerDictionary { len, inner: self });
+                self.inner.complete_object_reference(index);
+                result
+            }
+        }
+    }
+
+    /// Visits a dictionary as a sequence of key-value pairs, for keys that can't be hashed
+    fn deserialize_dictionary_seq<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.read_header()? {
+            Header::Reference(index) => self.deserialize_seq_reference(index, visitor),
+            Header::Inline { header, index } => {
+                let len = self.inner.check_count(header >> 1, 2)?;
+                let _weak_keys = self.inner.read_flag()?;
+                let result = visitor.visit_seq(ByteDeserializerPairs { len, inner: self });
+                self.inner.complete_object_reference(index);
+                result
+            }
+        }
+    }
+
+    /// Visits a dictionary as the enum that a [`value::Dictionary`] expects
+    fn deserialize_dictionary_enum<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.read_marker()?;
+        match self.read_header()? {
+            Header::Reference(index) => {
+                let checkpoint = self.inner.seek_object_reference(index)?;
+                let stats = self.stats.take();
+                let result = self
+                    .expect_marker(Marker::Dictionary)
+                    .and_then(|()| self.deserialize_dictionary_enum(visitor));
+                self.stats = stats;
+                self.inner.restore(checkpoint);
+                result
+            }
+            Header::Inline { header, index } => {
+                let len = self.inner.check_count(header >> 1, 2)?;
+                let weak_keys = self.inner.read_flag()?;
+                let result = visitor.visit_enum(ByteDeserializerWeakKeys {
+                    weak_keys,
+                    len,
+                    inner: self,
+                });
+                self.inner.complete_object_reference(index);
+                result
+            }
+        }
+    }
+
+    /// Like [`Self::deserialize_reference`], for a value that is requested as a sequence
+    fn deserialize_seq_reference<V>(&mut self, index: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let checkpoint = self.inner.seek_object_reference(index)?;
+        let stats = self.stats.take();
+        let result = serde::Deserializer::deserialize_seq(&mut *self, visitor);
+        self.stats = stats;
+        self.inner.restore(checkpoint);
+        result
+    }
+
+    fn deserialize_byte_array<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.read_header()? {
+            Header::Reference(index) => self.deserialize_reference::<V, N, F>(index, visitor),
+            Header::Inline { header, index } => {
+                let len = self.inner.check_count(header >> 1, 1)?;
+                let bytes = self.inner.read_bytes(len)?;
+                self.inner.complete_object_reference(index);
+                visitor.visit_borrowed_bytes(bytes)
+            }
+        }
+    }
+
+    /// Visits a byte array as a sequence of `u8`, for targets like `Vec<u8>`
+    fn deserialize_byte_seq<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.read_header()? {
+            Header::Reference(index) => self.deserialize_seq_reference(index, visitor),
+            Header::Inline { header, index } => {
+                let len = self.inner.check_count(header >> 1, 1)?;
+                let bytes = self.inner.read_bytes(len)?;
+                self.inner.complete_object_reference(index);
+                // the exact size hint lets the visitor reserve the length once
+                visitor.visit_seq(serde::de::value::SeqDeserializer::new(
+                    bytes.iter().copied(),
+                ))
+            }
+        }
+    }
+
+    fn deserialize_date<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.read_header()? {
+            Header::Reference(index) => self.deserialize_reference::<V, N, F>(index, visitor),
+            Header::Inline { index, .. } => {
+                // milliseconds since the epoch, negative before 1970
+                let millis = self.inner.read_double()?;
+                self.inner.complete_object_reference(index);
+                F::visit_double(visitor, millis)
+            }
+        }
+    }
+
+    fn deserialize_scalar<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.inner.options.unwrap_single_element {
+            // marker, one dense element, no associative keys
+            const SINGLE_ELEMENT_ARRAY: [u8; 3] = [Marker::Array as u8, 0x03, 0x01];
+            if self.inner.remaining().starts_with(&SINGLE_ELEMENT_ARRAY) {
+                self.read_marker()?;
+                // the array still takes its slot in the object reference table
+                let Header::Inline { index, .. } = self.read_header()? else {
+                    unreachable!("the header of a single element array is inline")
+                };
+                self.inner.read_string()?;
+                let result = self.deserialize_into::<V, N, F>(visitor);
+                self.inner.complete_object_reference(index);
+                return result;
+            }
+        }
+        self.deserialize_into::<V, N, F>(visitor)
+    }
+
+    fn deserialize_any_with<V, F: VisitDouble>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.inner.options.number_hint {
+            NumberHint::U32 => self.deserialize_into::<V, u32, F>(visitor),
+            NumberHint::I64 => self.deserialize_into::<V, i64, F>(visitor),
+            NumberHint::F64 => self.deserialize_into::<V, f64, F>(visitor),
+        }
+    }
+
+    fn deserialize_into<V, N: VisitInt, F: VisitDouble>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let marker = self.read_marker()?;
+        match marker {
+            Marker::Undefined => visitor.visit_none(),
+            Marker::Null => visitor.visit_unit(),
+            Marker::False => visitor.visit_bool(false),
+            Marker::True => visitor.visit_bool(true),
+            Marker::Integer => {
+                let value = self.inner.read_i29()?;
+                if self.inner.options.strict_casts && !N::fits(value) {
+                    return Err(Error {
+                        kind: ErrorKind::IntegerRange(value),
+                    });
+                }
+                N::visit_int(visitor, value)
+            }
+            Marker::Double => {
+                let value = self.inner.read_double()?;
+                if F::INTEGER && self.inner.options.strict_integers {
+                    if value.fract() != 0.0 {
+                        return Err(Error {
+                            kind: ErrorKind::NonIntegralDouble,
+                        });
+                    }
+                    if value.abs() > MAX_SAFE_INTEGER {
+                        return Err(Error {
+                            kind: ErrorKind::InexactDouble,
+                        });
+                    }
+                }
+                if F::INTEGER && self.inner.options.strict_casts && !F::fits(value) {
+                    let kind = if value.fract() != 0.0 {
+                        ErrorKind::NonIntegralDouble
+                    } else {
+                        ErrorKind::DoubleRange
+                    };
+                    return Err(Error { kind });
+                }
+                F::visit_double(visitor, value)
+            }
+            Marker::String => visitor.visit_borrowed_str(self.inner.read_string()?),
+            Marker::XmlDoc => self.deserialize_xml::<V, N, F>(visitor),
+            Marker::Date => self.deserialize_date::<V, N, F>(visitor),
+            Marker::Array => self.deserialize_array::<V, N, F>(visitor),
+            Marker::Object => self.deserialize_object::<V, N, F>(visitor),
+            Marker::Xml => self.deserialize_xml::<V, N, F>(visitor),
+            Marker::ByteArray => self.deserialize_byte_array::<V, N, F>(visitor),
+            Marker::VectorInt
+            | Marker::VectorUInt
+            | Marker::VectorDouble
+            | Marker::VectorObject => self.deserialize_vector::<V, N, F>(marker, visitor),
+            Marker::Dictionary => self.deserialize_dictionary::<V, N, F>(visitor),
+        }
+    }
+}
+
+impl From<format::Error> for Error {
+    fn from(e: format::Error) -> Self {
+        Self {
+            kind: ErrorKind::Format(e),
+        }
+    }
+}
+
+pub fn deserialize<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<T, Error> {
+    let mut deserializer = ByteDeserializer::from_bytes(input);
+    T::deserialize(&mut deserializer)
+}
+
+/// Like [`deserialize`], but fails early if the value doesn't start with `marker`
+pub fn deserialize_expecting<'de, T: Deserialize<'de>>(
+    input: &'de [u8],
+    marker: Marker,
+) -> Result<T, Error> {
+    let mut deserializer = ByteDeserializer::from_bytes(input);
+    deserializer.expect_marker(marker)?;
+    T::deserialize(&mut deserializer)
+}
+
+/// Decode a single value and return the bytes after it, e.g. a footer of the container
+pub fn deserialize_prefix<'de, T: Deserialize<'de>>(
+    input: &'de [u8],
+) -> Result<(T, &'de [u8]), Error> {
+    let mut deserializer = ByteDeserializer::from_bytes(input);
+    let value = T::deserialize(&mut deserializer)?;
+    Ok((value, deserializer.inner.remaining()))
+}
+
+/// Like [`deserialize`], but a failure comes with the offset in the input where it happened
+pub fn deserialize_located<'de, T: Deserialize<'de>>(
+    input: &'de [u8],
+) -> Result<T, (Error, usize)> {
+    let mut deserializer = ByteDeserializer::from_bytes(input);
+    T::deserialize(&mut deserializer).map_err(|e| (e, deserializer.position()))
+}
+
+/// Decode values until the input is exhausted, for streams of concatenated values
+pub fn deserialize_all<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<Vec<T>, Error> {
+    let mut deserializer = ByteDeserializer::from_bytes(input);
+    let mut values = Vec::new();
+    while !deserializer.inner.is_empty() {
+        values.push(T::deserialize(&mut deserializer)?);
+    }
+    Ok(values)
+}
+
+/// Streams the AMF3 value in `input` to `writer` as JSON
+#[cfg(feature = "json")]
+pub fn transcode_to_json<W: std::io::Write>(input: &[u8], writer: W) -> Result<(), Error> {
+    let mut deserializer = ByteDeserializer::from_bytes(input);
+    let mut writer = transcode::CaptureWriter {
+        inner: writer,
+        error: None,
+    };
+    let mut serializer = serde_json::Serializer::new(&mut writer);
+    match transcode::transcode(&mut deserializer, &mut serializer) {
+        Ok(()) => Ok(()),
+        Err(transcode::TranscodeError::Decode(error)) => Err(error),
+        Err(transcode::TranscodeError::Serialize(message)) => {
+            let error = match writer.error {
+                Some(error) => serde_json::Error::io(error),
+                None => serde::ser::Error::custom(message),
+            };
+            Err(Error {
+                kind: ErrorKind::Json(JsonError(std::sync::Arc::new(error))),
+            })
+        }
+    }
+}
+
+impl<'de> serde::Deserializer<'de> for &mut ByteDeserializer<'de> {
+    type Error = Error;
+
+    forward_to_deserialize_any! { tuple tuple_struct map struct identifier }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.inner.options.integral_doubles {
+            self.deserialize_any_with::<V, IntegralDouble>(visitor)
+        } else {
+            self.deserialize_any_with::<V, f64>(visitor)
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.inner.peek_marker()? {
+            Marker::Undefined | Marker::Null => {
+                self.read_marker()?;
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, u32, f64>(visitor)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, u32, f64>(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, u32, f64>(visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.inner.options.unwrap_single_element
+            && matches!(
+                self.inner.peek_marker()?,
+                Marker::False | Marker::True | Marker::Integer | Marker::Double | Marker::String
+            )
+        {
+            // a scalar where a sequence was requested
+            return visitor.visit_seq(ByteDeserializerSeq {
+                inner: self,
+                len: 1,
+            });
+        }
+        match self.inner.peek_marker()? {
+            Marker::ByteArray => {
+                self.read_marker()?;
+                self.deserialize_byte_seq(visitor)
+            }
+            Marker::Dictionary => {
+                self.read_marker()?;
+                self.deserialize_dictionary_seq(visitor)
+            }
+            _ => self.deserialize_into::<V, u32, f64>(visitor),
+        }
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, i8, i8>(visitor)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, i16, i16>(visitor)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, i32, i32>(visitor)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, i64, i64>(visitor)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, u8, u8>(visitor)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, u16, u16>(visitor)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, u32, u32>(visitor)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, u64, u64>(visitor)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, f32, f32>(visitor)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, f64, f64>(visitor)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.inner.options.char_code_points && self.inner.peek_byte()? == Marker::Integer as u8 {
+            self.read_marker()?;
+            let code_point = self.inner.read_u29()?;
+            return match char::from_u32(code_point) {
+                Some(c) => visitor.visit_char(c),
+                None => Err(serde::de::Error::invalid_value(
+                    serde::de::Unexpected::Unsigned(code_point.into()),
+                    &visitor,
+                )),
+            };
+        }
+        // a string with a single character
+        self.deserialize_scalar::<V, u32, f64>(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, u32, f64>(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_scalar::<V, u32, f64>(visitor)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.inner.peek_byte()? != Marker::Object as u8 {
+            return self.deserialize_scalar::<V, u32, f64>(visitor);
+        }
+        self.read_marker()?;
+        match self.read_header()? {
+            Header::Reference(index) => {
+                let checkpoint = self.inner.seek_object_reference(index)?;
+                let stats = self.stats.take();
+                let result = self.deserialize_unit(visitor);
+                self.stats = stats;
+                self.inner.restore(checkpoint);
+                result
+            }
+            Header::Inline { header, index } => {
+                let traits = self.inner.read_traits(ObjectHeader::new(header))?;
+                let traits = self.inner.traits(traits);
+                let empty = !traits.externalizable && traits.members.is_empty();
+                // dynamic objects are empty if the first member name is the end marker
+                if empty && (!traits.dynamic || self.inner.read_string()?.is_empty()) {
+                    self.inner.complete_object_reference(index);
+                    visitor.visit_unit()
+                } else {
+                    Err(serde::de::Error::invalid_type(
+                        serde::de::Unexpected::Map,
+                        &visitor,
+                    ))
+                }
+            }
+        }
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if value::is_marker_token(name) {
+            visitor.visit_enum(ByteDeserializerMarker { inner: self })
+        } else if name == value::DICTIONARY_TOKEN && self.inner.peek_marker()? == Marker::Dictionary
+        {
+            self.deserialize_dictionary_enum(visitor)
+        } else {
+            visitor.visit_newtype_struct(self)
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.inner.peek_marker()? {
+            Marker::String => {
+                // a unit variant by name, e.g. the tag of an adjacently tagged enum
+                self.read_marker()?;
+                let variant = self.inner.read_string()?;
+                visitor.visit_enum(BorrowedStrDeserializer::new(variant))
+            }
+            marker => Err(serde::de::Error::invalid_type(
+                serde::de::Unexpected::Other(&format!("a value with marker {:?}", marker)),
+                &visitor,
+            )),
+        }
+    }
+
+    /*fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        todo!()
+    }*/
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.inner.skip()?;
+        visitor.visit_none()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::{format, ByteDeserializer, DeserializerOptions, Error, ErrorKind, NumberHint};
+
+    const EOS_ERROR: Error = Error {
+        kind: ErrorKind::Format(format::Error::EndOfStream),
+    };
+
+    #[test]
+    fn test_error_clone() {
+        let error = super::deserialize::<bool>(&[0x12]).unwrap_err();
+        let clone = error.clone();
+        assert_eq!(error, clone);
+        assert_ne!(clone, EOS_ERROR);
+        assert_eq!(EOS_ERROR.clone(), EOS_ERROR);
+    }
+
+    #[test]
+    fn test_invalid_marker_byte() {
+        let error = super::deserialize::<bool>(&[0x12]).unwrap_err();
+        assert_eq!(error.invalid_marker_byte(), Some(0x12));
+        assert_eq!(EOS_ERROR.invalid_marker_byte(), None);
+    }
+
+    #[test]
+    fn test_bool() {
+        assert_eq!(super::deserialize::<bool>(&[]), Err(EOS_ERROR));
+        assert_eq!(super::deserialize::<bool>(&[0x02]), Ok(false));
+        assert_eq!(super::deserialize::<bool>(&[0x03]), Ok(true));
+    }
+
+    #[test]
+    fn test_integer() {
+        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(5u8));
+        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(5u16));
+        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(5u32));
+        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(5u64));
+    }
+
+    #[test]
+    fn test_fixed_capacity() {
+        let input = [0x09, 0x07, 0x01, 0x04, 1, 0x04, 2, 0x04, 3];
+        let values: arrayvec::ArrayVec<u32, 3> = super::deserialize(&input).unwrap();
+        assert_eq!(values.as_slice(), [1, 2, 3]);
+        assert!(matches!(
+            super::deserialize::<arrayvec::ArrayVec<u32, 2>>(&input),
+            Err(Error {
+                kind: ErrorKind::Custom(_)
+            })
+        ));
+    }
+
+    #[test]
+    fn test_smallvec() {
+        let input = [0x09, 0x07, 0x01, 0x04, 1, 0x04, 2, 0x04, 3];
+        let values: smallvec::SmallVec<[u32; 4]> = super::deserialize(&input).unwrap();
+        assert_eq!(values.as_slice(), [1, 2, 3]);
+        assert!(!values.spilled());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Num {
+        Int(u32),
+        Float(f64),
+    }
+
+    #[test]
+    fn test_untagged_number() {
+        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(Num::Int(5)));
+        assert_eq!(
+            super::deserialize(&[0x05, 0, 0, 0, 0, 0, 0, 0xD0, 0x3F]),
+            Ok(Num::Float(0.25))
+        );
+        // negative integers don't fit the first variant
+        assert_eq!(
+            super::deserialize(&[0x04, 0xFF, 0xFF, 0xFF, 0xFF]),
+            Ok(Num::Float(-1.0))
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Shape {
+        Circle { radius: f64 },
+        Rect { width: u32, height: u32 },
+        Other(std::collections::BTreeMap<u32, String>),
+    }
+
+    #[test]
+    fn test_untagged() {
+        let input = b"\x09\x07\x01\
+            \x0A\x0B\x01\x0Dradius\x05\x00\x00\x00\x00\x00\x00\xD0\x3F\x01\
+            \x0A\x0B\x01\x0Bwidth\x04\x02\x0Dheight\x04\x03\x01\
+            \x11\x03\x00\x04\x01\x0B\x09<a/>";
+        assert_eq!(
+            super::deserialize(input),
+            Ok(vec![
+                Shape::Circle { radius: 0.25 },
+                Shape::Rect {
+                    width: 2,
+                    height: 3
+                },
+                Shape::Other([(1, String::from("<a/>"))].into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_vector_header() {
+        let input = [0x0D, 0x05, 0x01, 0x0D, 0x02];
+        let mut deserializer = ByteDeserializer::from_bytes(&input);
+        deserializer.inner.read_marker().unwrap();
+        assert_eq!(
+            deserializer.read_vector_header(),
+            Ok(super::VectorHeader::Inline {
+                index: 0,
+                count: 2,
+                fixed: true
+            })
+        );
+        deserializer.inner.read_marker().unwrap();
+        assert_eq!(
+            deserializer.read_vector_header(),
+            Ok(super::VectorHeader::Reference(1))
+        );
+    }
+
+    #[test]
+    fn test_unknown_markers() {
+        /// A made-up marker `0x12` with a one byte length
+        fn handler(marker: u8, input: &[u8]) -> Option<usize> {
+            match (marker, input.first()) {
+                (0x12, Some(&len)) => Some(1 + usize::from(len)),
+                _ => None,
+            }
+        }
+
+        // [<0x12 with 2 bytes>, 5]
+        let input = [0x09, 0x05, 0x01, 0x12, 0x02, 0xAB, 0xCD, 0x04, 0x05];
+        assert_eq!(
+            super::deserialize::<Vec<Option<u32>>>(&input),
+            Err(format::Error::InvalidMarker(0x12).into())
+        );
+        let mut deserializer = ByteDeserializer::from_bytes(&input).unknown_markers(Some(handler));
+        assert_eq!(
+            Vec::<Option<u32>>::deserialize(&mut deserializer),
+            Ok(vec![None, Some(5)])
+        );
+
+        // as an ignored member
+        let input = b"\x0A\x0B\x01\x03c\x12\x01\xFF\x03a\x04\x01\x03b\x04\x02\x01";
+        let mut deserializer = ByteDeserializer::from_bytes(input).unknown_markers(Some(handler));
+        assert_eq!(
+            Test::deserialize(&mut deserializer),
+            Ok(Test { a: 1, b: 2 })
+        );
+
+        // the handler doesn't know this one
+        let input = [0x13, 0x00];
+        let mut deserializer = ByteDeserializer::from_bytes(&input).unknown_markers(Some(handler));
+        assert_eq!(
+            Option::<u32>::deserialize(&mut deserializer),
+            Err(format::Error::InvalidMarker(0x13).into())
+        );
+    }
+
+    #[test]
+    fn test_entries() {
+        // [k: 1, "a", 2.5], then a reference to it, then {x: true}
+        let input = b"\x09\x05\x03k\x04\x01\x01\x06\x03a\x05\0\0\0\0\0\0\x04\x40\
+            \x09\x00\x0A\x0B\x01\x03x\x03\x01";
+        let expected = vec![
+            (String::from("k"), crate::Value::Integer(1)),
+            (String::from("0"), crate::Value::String(String::from("a"))),
+            (String::from("1"), crate::Value::Double(2.5)),
+        ];
+        let mut deserializer = ByteDeserializer::from_bytes(input);
+        let mut entries = Vec::new();
+        for entry in deserializer.entries().unwrap() {
+            entries.push(entry.unwrap());
+        }
+        assert_eq!(entries, expected);
+        let entries = deserializer.entries().unwrap();
+        assert_eq!(entries.collect::<Result<Vec<_>, _>>(), Ok(expected.clone()));
+        let entries = deserializer.entries().unwrap();
+        assert_eq!(
+            entries.collect::<Result<Vec<_>, _>>(),
+            Ok(vec![(String::from("x"), crate::Value::Bool(true))])
+        );
+        assert!(deserializer.into_format().remaining().is_empty());
+
+        // only the first entry, then the reference and the object
+        let mut deserializer = ByteDeserializer::from_bytes(input);
+        let mut entries = deserializer.entries().unwrap();
+        assert_eq!(entries.next(), Some(Ok(expected[0].clone())));
+        drop(entries);
+        let entries = deserializer.entries().unwrap();
+        assert_eq!(entries.collect::<Result<Vec<_>, _>>(), Ok(expected));
+        assert_eq!(
+            crate::Value::deserialize(&mut deserializer),
+            Ok(crate::Value::Object(vec![(
+                String::from("x"),
+                crate::Value::Bool(true)
+            )]))
+        );
+
+        let mut deserializer = ByteDeserializer::from_bytes(b"\x04\x01");
+        assert!(deserializer.entries().is_err());
+    }
+
+    #[test]
+    fn test_reference_manifest() {
+        // ["a", "b", "a", {a: "b"}], with references to the repeated strings
+        let input = b"\x09\x09\x01\x06\x03a\x06\x03b\x06\x00\x0A\x0B\x01\x00\x06\x02\x01";
+        let mut deserializer = ByteDeserializer::from_bytes(input);
+        let value = crate::Value::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            value,
+            crate::Value::Array(vec![
+                crate::Value::String(String::from("a")),
+                crate::Value::String(String::from("b")),
+                crate::Value::String(String::from("a")),
+                crate::Value::Object(vec![(
+                    String::from("a"),
+                    crate::Value::String(String::from("b"))
+                )]),
+            ])
+        );
+        assert_eq!(
+            deserializer.into_reference_manifest(),
+            super::ReferenceManifest {
+                strings: vec!["a", "b"],
+                traits: vec![""],
+                objects: vec![0, 11],
+            }
+        );
+    }
+
+    #[test]
+    fn test_byte_array_reference() {
+        // [<ByteArray 0xAB 0xCD>, a reference to it]
+        let input = [0x09, 0x05, 0x01, 0x0C, 0x05, 0xAB, 0xCD, 0x0C, 0x02];
+        let (first, second) = super::deserialize::<(&[u8], &[u8])>(&input).unwrap();
+        assert_eq!(first, [0xAB, 0xCD]);
+        // both borrow the bytes of the inline value
+        assert_eq!(first.as_ptr(), input[5..].as_ptr());
+        assert_eq!(second.as_ptr(), first.as_ptr());
+        assert_eq!(second.len(), 2);
+    }
+
+    #[test]
+    fn test_from_format() {
+        // a string, two bytes of framing, and a reference to the string
+        let input = b"\x06\x0BHello\xCA\xFE\x06\x00";
+        let mut deserializer = ByteDeserializer::from_bytes(input);
+        assert_eq!(<&str>::deserialize(&mut deserializer), Ok("Hello"));
+        let mut format = deserializer.into_format();
+        assert_eq!(format.read_raw(2), Ok(&b"\xCA\xFE"[..]));
+        assert_eq!(format.position(), 9);
+        let mut deserializer = ByteDeserializer::from_format(format);
+        assert_eq!(<&str>::deserialize(&mut deserializer), Ok("Hello"));
+        assert!(deserializer.into_format().remaining().is_empty());
+    }
+
+    #[test]
+    fn test_vector_fixed_flag() {
+        let input = [0x0D, 0x03, 0x02, 0x01, 0x00, 0x00, 0x00];
+        assert_eq!(
+            super::deserialize::<Vec<i32>>(&input),
+            Err(format::Error::InvalidFlag(0x02).into())
+        );
+    }
+
+    #[test]
+    fn test_vector() {
+        let input = b"\x09\x09\x01\x0D\x05\x00\xFF\xFF\xFF\xFF\x02\x00\x00\x00\
+            \x0E\x03\x01\xFF\xFF\xFF\xFF\x0F\x03\x00\x00\x00\x00\x00\x00\x00\xD0\x3F\
+            \x10\x05\x00\x01\x04\x03\x0D\x02";
+        assert_eq!(
+            super::deserialize::<(Vec<i32>, Vec<u32>, Vec<f64>, (u32, Vec<i32>))>(input),
+            Ok((vec![-1, 2], vec![u32::MAX], vec![0.25], (3, vec![-1, 2])))
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_transcode_to_json() {
+        let input = b"\x0A\x0B\x01\x03a\x09\x07\x01\x04\x01\x05\x00\x00\x00\x00\x00\x00\xD0\x3F\
+            \x06\x0BHello\x03b\x03\x03c\x01\x01";
+        let mut output = Vec::new();
+        super::transcode_to_json(input, &mut output).unwrap();
+        let value = super::deserialize::<serde_json::Value>(input).unwrap();
+        assert_eq!(output, value.to_string().as_bytes());
+        assert_eq!(output, br#"{"a":[1,0.25,"Hello"],"b":true,"c":null}"#);
+
+        let error = super::transcode_to_json(&input[..12], Vec::new()).unwrap_err();
+        assert_eq!(error, EOS_ERROR);
+        let error = super::transcode_to_json(b"\x09\x03\x01\x12", Vec::new()).unwrap_err();
+        assert_eq!(error.invalid_marker_byte(), Some(0x12));
+
+        let mut output = [0u8; 4];
+        let error = super::transcode_to_json(input, &mut output[..]).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::Json(_)));
+        assert!(std::error::Error::source(&error).is_some());
+
+        // a dictionary with an object as its key
+        let input = b"\x11\x03\x00\x0A\x0B\x01\x03a\x04\x01\x01\x04\x07";
+        let error = super::transcode_to_json(input, Vec::new()).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::Json(_)));
+        assert_eq!(error.to_string(), "JSON error: key must be a string");
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_member_order() {
+        // sealed members `z` and `a`, then the dynamic members `m` and `b`
+        let input = b"\x0A\x2B\x01\x03z\x03a\x04\x01\x04\x02\x03m\x04\x03\x03b\x04\x04\x01";
+        let value = super::deserialize::<serde_json::Value>(input).unwrap();
+        assert_eq!(value.to_string(), r#"{"z":1,"a":2,"m":3,"b":4}"#);
+        let mut output = Vec::new();
+        super::transcode_to_json(input, &mut output).unwrap();
+        assert_eq!(output, value.to_string().as_bytes());
+    }
+
+    #[test]
+    fn test_input_limit() {
+        // ["Hello", "World"]
+        let input = b"\x09\x05\x01\x06\x0BHello\x06\x0BWorld";
+        let mut deserializer = ByteDeserializer::from_bytes(input).input_limit(Some(10));
+        let error = Vec::<&str>::deserialize(&mut deserializer).unwrap_err();
+        assert!(error.is_input_limit_exceeded());
+
+        let mut deserializer = ByteDeserializer::from_bytes(input).input_limit(Some(input.len()));
+        assert_eq!(
+            Vec::<&str>::deserialize(&mut deserializer),
+            Ok(vec!["Hello", "World"])
+        );
+
+        // a truncated input is still the end of the stream
+        let options = DeserializerOptions::new().input_limit(Some(64));
+        let mut deserializer = ByteDeserializer::with_options(&input[..10], options);
+        let error = Vec::<&str>::deserialize(&mut deserializer).unwrap_err();
+        assert!(!error.is_input_limit_exceeded());
+        assert_eq!(error, EOS_ERROR);
+    }
+
+    #[test]
+    fn test_vector_fixed_array() {
+        let input = b"\x0D\x07\x00\x01\0\0\0\x02\0\0\0\xFF\xFF\xFF\xFF";
+        assert_eq!(super::deserialize::<[i32; 3]>(input), Ok([1, 2, -1]));
+        assert!(super::deserialize::<[i32; 2]>(input).is_err());
+        assert!(super::deserialize::<[i32; 4]>(input).is_err());
+
+        let input = b"\x0F\x05\x00\0\0\0\0\0\0\xD0\x3F\0\0\0\0\0\0\xF0\x3F";
+        assert_eq!(super::deserialize::<[f64; 2]>(input), Ok([0.25, 1.0]));
+        assert!(super::deserialize::<[f64; 1]>(input).is_err());
+    }
+
+    #[test]
+    fn test_array_fixed_length() {
+        // [1, 2, 3]
+        let input = b"\x09\x07\x01\x04\x01\x04\x02\x04\x03";
+        let error: Error = serde::de::Error::invalid_length(3, &"no more elements than the target");
+        assert_eq!(super::deserialize::<[u32; 3]>(input), Ok([1, 2, 3]));
+        assert_eq!(super::deserialize::<[u32; 2]>(input), Err(error.clone()));
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Pair {
+            a: [u32; 2],
+            b: u32,
+        }
+        let input = b"\x0A\x0B\x01\x03a\x09\x07\x01\x04\x01\x04\x02\x04\x03\x03b\x04\x04\x01";
+        assert_eq!(super::deserialize::<Pair>(input), Err(error));
+    }
+
+    #[test]
+    fn test_rewalk_budget() {
+        // every array contains the previous one twice
+        let mut input = vec![0x09, 0x15, 0x01, 0x09, 0x01, 0x01];
+        for i in 1..10u8 {
+            input.extend_from_slice(&[0x09, 0x05, 0x01, 0x09, i << 1, 0x09, i << 1]);
+        }
+        assert!(super::deserialize::<crate::Value>(&input).is_ok());
+        let mut deserializer = ByteDeserializer::from_bytes(&input).rewalk_budget(100);
+        assert_eq!(
+            crate::Value::deserialize(&mut deserializer),
+            Err(format::Error::WorkBudgetExceeded.into())
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Empty;
+
+    #[test]
+    fn test_empty_object() {
+        assert_eq!(super::deserialize(b"\x0A\x03\x01"), Ok(()));
+        assert_eq!(super::deserialize(b"\x0A\x0B\x01\x01"), Ok(Empty));
+        assert_eq!(super::deserialize(b"\x01"), Ok(()));
+        assert_eq!(
+            super::deserialize(b"\x09\x05\x01\x0A\x03\x01\x0A\x02"),
+            Ok(((), Empty))
+        );
+        assert!(super::deserialize::<()>(b"\x0A\x0B\x01\x03a\x04\x05\x01").is_err());
+    }
+
+    #[test]
+    fn test_disable_references() {
+        let input = [0x09, 0x05, 0x01, 0x06, 0x03, b'a', 0x06, 0x00];
+        assert_eq!(super::deserialize(&input), Ok(vec!["a", "a"]));
+        let mut deserializer = ByteDeserializer::from_bytes(&input).disable_references(true);
+        assert_eq!(
+            Vec::<&str>::deserialize(&mut deserializer),
+            Err(format::Error::ReferencesDisabled.into())
+        );
+        let mut deserializer = ByteDeserializer::from_bytes(&input[3..6]).disable_references(true);
+        assert_eq!(<&str>::deserialize(&mut deserializer), Ok("a"));
+        let input = [0x09, 0x05, 0x01, 0x09, 0x01, 0x01, 0x09, 0x02];
+        let mut deserializer = ByteDeserializer::from_bytes(&input).disable_references(true);
+        assert_eq!(
+            Vec::<Vec<u32>>::deserialize(&mut deserializer),
+            Err(format::Error::ReferencesDisabled.into())
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Position {
+        x: f64,
+        y: f32,
+    }
+
+    #[test]
+    fn test_char() {
+        assert_eq!(super::deserialize(b"\x06\x03A"), Ok('A'));
+        assert_eq!(super::deserialize(b"\x06\x05\xC3\xA9"), Ok('é'));
+        assert!(super::deserialize::<char>(b"\x06\x05AB").is_err());
+        assert!(super::deserialize::<char>(&[0x04, 0x41]).is_err());
+
+        let mut deserializer = ByteDeserializer::from_bytes(&[0x04, 0x41]).char_code_points(true);
+        assert_eq!(char::deserialize(&mut deserializer), Ok('A'));
+        let mut deserializer = ByteDeserializer::from_bytes(b"\x06\x03A").char_code_points(true);
+        assert_eq!(char::deserialize(&mut deserializer), Ok('A'));
+        // a surrogate and a value beyond U+10FFFF
+        for input in [[0x04, 0x83, 0xB0, 0x00], [0x04, 0xC4, 0x80, 0x00]] {
+            let mut deserializer = ByteDeserializer::from_bytes(&input).char_code_points(true);
+            assert!(matches!(
+                char::deserialize(&mut deserializer),
+                Err(Error {
+                    kind: ErrorKind::Custom(_)
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_non_zero() {
+        use std::num::{NonZeroU32, NonZeroU64};
+
+        let value = super::deserialize::<NonZeroU32>(&[0x04, 0x05]);
+        assert_eq!(value.map(NonZeroU32::get), Ok(5));
+        let value = super::deserialize::<NonZeroU64>(&[0x04, 0x05]);
+        assert_eq!(value.map(NonZeroU64::get), Ok(5));
+        assert!(matches!(
+            super::deserialize::<NonZeroU32>(&[0x04, 0x00]),
+            Err(Error {
+                kind: ErrorKind::Custom(_)
+            })
+        ));
+        assert!(super::deserialize::<NonZeroU64>(&[0x04, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_integer_as_float() {
+        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(5.0f64));
+        assert_eq!(
+            super::deserialize(&[0x04, 0xFF, 0xFF, 0xFF, 0xFF]),
+            Ok(-1.0f32)
+        );
+        let input = b"\x0A\x0B\x01\x03x\x04\x05\x03y\x05\x00\x00\x00\x00\x00\x00\xD0\x3F\x01";
+        assert_eq!(super::deserialize(input), Ok(Position { x: 5.0, y: 0.25 }));
+    }
+
+    #[test]
+    fn test_double() {
+        assert_eq!(super::deserialize(&[0x05, 0, 0, 0, 0, 0, 0, 0, 0]), Ok(0.0));
+        assert_eq!(
+            super::deserialize(&[0x05, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Ok(0.0f32)
+        );
+        assert_eq!(
+            super::deserialize(&[0x05, 0, 0, 0, 0, 0, 0, 0xD0, 0x3F]),
+            Ok(0.25)
+        );
+        assert_eq!(
+            super::deserialize(&[0x05, 0, 0, 0, 0, 0, 0, 0xD0, 0x3F]),
+            Ok(0.25f32)
+        );
+        assert_eq!(
+            super::deserialize(&[0x05, 0x9A, 0x99, 0x99, 0x99, 0x99, 0x99, 0xB9, 0x3F]),
+            Ok(0.1)
+        );
+    }
+
+    #[test]
+    fn test_string() {
+        assert_eq!(super::deserialize(b"\x06\x0BHello"), Ok("Hello"));
+    }
+
+    #[test]
+    fn test_all() {
+        assert_eq!(
+            super::deserialize_all::<u32>(&[0x04, 0x01, 0x04, 0x02, 0x04, 0x03]),
+            Ok(vec![1, 2, 3])
+        );
+        assert_eq!(super::deserialize_all::<u32>(&[]), Ok(vec![]));
+        assert_eq!(
+            super::deserialize_all::<u32>(&[0x04, 0x01, 0x04]),
+            Err(EOS_ERROR)
+        );
+    }
+
+    #[test]
+    fn test_option() {
+        // undefined
+        assert_eq!(super::deserialize::<Option<u32>>(b"\x00"), Ok(None));
+        // null
+        assert_eq!(super::deserialize::<Option<u32>>(b"\x01"), Ok(None));
+        assert_eq!(super::deserialize::<Option<u32>>(b"\x04\x05"), Ok(Some(5)));
+        for input in [&b"\x00"[..], b"\x01"] {
+            assert_eq!(super::deserialize::<Option<String>>(input), Ok(None));
+        }
+        assert_eq!(
+            super::deserialize::<Option<String>>(b"\x06\x03a"),
+            Ok(Some(String::from("a")))
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct Strip {
+        action_index: u32,
+        #[serde(rename = "stateID")]
+        state_id: u32,
+    }
+
+    #[test]
+    fn test_renamed_fields() {
+        // sealed members
+        let input = b"\x0A\x23\x01\x17actionIndex\x0FstateID\x04\x04\x04\x07";
+        let strip = Strip {
+            action_index: 4,
+            state_id: 7,
+        };
+        assert_eq!(super::deserialize::<Strip>(input), Ok(strip));
+        // dynamic members
+        let input = b"\x0A\x0B\x01\x17actionIndex\x04\x04\x0FstateID\x04\x07\x01";
+        let strip = Strip {
+            action_index: 4,
+            state_id: 7,
+        };
+        assert_eq!(super::deserialize::<Strip>(input), Ok(strip));
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Data<'a> {
+        #[serde(borrow)]
+        data: &'a [u8],
+    }
+
+    #[test]
+    fn test_borrowed_bytes() {
+        let input = b"\x0A\x0B\x01\x09data\x0C\x05\xAB\xCD\x01";
+        let value = super::deserialize::<Data>(input).unwrap();
+        assert_eq!(value.data, &[0xAB, 0xCD]);
+        assert_eq!(value.data.as_ptr(), input[10..].as_ptr());
+    }
+
+    #[test]
+    fn test_number_hint() {
+        use serde_json::{json, Value};
+        let input = b"\x04\xFF\xFF\xFF\xFF";
+        assert_eq!(super::deserialize::<i32>(input), Ok(-1));
+        assert_eq!(super::deserialize::<Value>(input), Ok(json!(-1)));
+        for (hint, value) in [
+            (NumberHint::U32, json!(u32::MAX)),
+            (NumberHint::I64, json!(-1)),
+            (NumberHint::F64, json!(-1.0)),
+        ] {
+            let mut deserializer = ByteDeserializer::from_bytes(input).number_hint(hint);
+            assert_eq!(Value::deserialize(&mut deserializer), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_huge_count() {
+        for input in [
+            &b"\x09\xFF\xFF\xFF\xFF\x01\x04\x01"[..],
+            &b"\x0A\xFF\xFF\xFF\xF3\x01\x03a"[..],
+            &b"\x0F\xFF\xFF\xFF\xFF\x00\x00\x00\x00\x00\x00\x00\xD0\x3F"[..],
+            &b"\x0D\x05\x00\x01\x00\x00\x00"[..],
+        ] {
+            assert_eq!(
+                super::deserialize::<crate::Value>(input),
+                Err(format::Error::EndOfStream.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_integral_doubles() {
+        use serde_json::Value;
+        let input = [
+            0x09, 0x05, 0x01, 0x05, 0, 0, 0, 0, 0, 0, 0x14, 0x40, 0x05, 0, 0, 0, 0, 0, 0, 0xD0,
+            0x3F,
+        ];
+        assert_eq!(
+            super::deserialize::<Value>(&input).unwrap().to_string(),
+            "[5.0,0.25]"
+        );
+        let mut deserializer = ByteDeserializer::from_bytes(&input).integral_doubles(true);
+        assert_eq!(
+            Value::deserialize(&mut deserializer).unwrap().to_string(),
+            "[5,0.25]"
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let input = b"\x09\x09\x01\x09\x03\x01\x04\x05\x06\x03a\x06\x00\x09\x02";
+        let mut deserializer = ByteDeserializer::from_bytes(input).with_stats();
+        crate::Value::deserialize(&mut deserializer).unwrap();
+        let stats = deserializer.into_stats().unwrap();
+        // the reference to the inner array counts, but not what it contains
+        assert_eq!(stats.count(super::Marker::Array), 3);
+        assert_eq!(stats.count(super::Marker::Integer), 1);
+        assert_eq!(stats.count(super::Marker::String), 2);
+        assert_eq!(stats.count(super::Marker::Double), 0);
+        assert_eq!(stats.total(), 6);
+        assert_eq!(ByteDeserializer::from_bytes(input).into_stats(), None);
+    }
+
+    #[test]
+    fn test_strict_integers() {
+        let three = [0x05, 0, 0, 0, 0, 0, 0, 0x08, 0x40];
+        let three_and_a_half = [0x05, 0, 0, 0, 0, 0, 0, 0x0C, 0x40];
+        assert_eq!(super::deserialize(&three_and_a_half), Ok(3i64));
+        let mut deserializer = ByteDeserializer::from_bytes(&three).strict_integers(true);
+        assert_eq!(i64::deserialize(&mut deserializer), Ok(3));
+        let mut deserializer =
+            ByteDeserializer::from_bytes(&three_and_a_half).strict_integers(true);
+        assert_eq!(
+            i64::deserialize(&mut deserializer),
+            Err(Error {
+                kind: ErrorKind::NonIntegralDouble
+            })
+        );
+        let mut deserializer =
+            ByteDeserializer::from_bytes(&three_and_a_half).strict_integers(true);
+        assert_eq!(f64::deserialize(&mut deserializer), Ok(3.5));
+    }
+
+    #[test]
+    fn test_strict_casts() {
+        let input = [0x04, 0x82, 0x2C];
+        assert_eq!(super::deserialize(&input), Ok(44u8));
+        let mut deserializer = ByteDeserializer::from_bytes(&input).strict_casts(true);
+        assert_eq!(
+            u8::deserialize(&mut deserializer),
+            Err(Error {
+                kind: ErrorKind::IntegerRange(300)
+            })
+        );
+        let mut deserializer = ByteDeserializer::from_bytes(&input).strict_casts(true);
+        assert_eq!(u16::deserialize(&mut deserializer), Ok(300));
+        let minus_one = [0x04, 0xFF, 0xFF, 0xFF, 0xFF];
+        let mut deserializer = ByteDeserializer::from_bytes(&minus_one).strict_casts(true);
+        assert!(u32::deserialize(&mut deserializer).is_err());
+        let mut deserializer = ByteDeserializer::from_bytes(&minus_one).strict_casts(true);
+        assert_eq!(f32::deserialize(&mut deserializer), Ok(-1.0));
+
+        let double = |v: f64| [&[0x05][..], &v.to_le_bytes()].concat();
+        for (input, kind) in [
+            (double(1e20), ErrorKind::DoubleRange),
+            (double(-1.0), ErrorKind::DoubleRange),
+            (double(3.5), ErrorKind::NonIntegralDouble),
+            (double(f64::NAN), ErrorKind::NonIntegralDouble),
+        ] {
+            assert!(super::deserialize::<u8>(&input).is_ok());
+            let mut deserializer = ByteDeserializer::from_bytes(&input).strict_casts(true);
+            assert_eq!(u8::deserialize(&mut deserializer), Err(Error { kind }));
+        }
+        let large = double(1e20);
+        let mut deserializer = ByteDeserializer::from_bytes(&large).strict_casts(true);
+        assert_eq!(
+            i32::deserialize(&mut deserializer),
+            Err(Error {
+                kind: ErrorKind::DoubleRange
+            })
+        );
+        let input = double(255.0);
+        let mut deserializer = ByteDeserializer::from_bytes(&input).strict_casts(true);
+        assert_eq!(u8::deserialize(&mut deserializer), Ok(255));
+        let input = double(18446744073709549568.0);
+        let mut deserializer = ByteDeserializer::from_bytes(&input).strict_casts(true);
+        assert_eq!(
+            u64::deserialize(&mut deserializer),
+            Ok(18446744073709549568)
+        );
+        let mut deserializer = ByteDeserializer::from_bytes(&large).strict_casts(true);
+        assert_eq!(f32::deserialize(&mut deserializer), Ok(1e20));
+    }
+
+    #[test]
+    fn test_strict_large_integers() {
+        let double = |v: f64| [&[0x05][..], &v.to_le_bytes()].concat();
+        let max_safe = double(9007199254740991.0);
+        let mut deserializer = ByteDeserializer::from_bytes(&max_safe).strict_integers(true);
+        assert_eq!(u64::deserialize(&mut deserializer), Ok((1 << 53) - 1));
+        // 2^53 + 1 is written as 2^53, which is ambiguous
+        for value in [1u64 << 53, (1 << 53) + 1] {
+            let bytes = crate::serialize(&value).unwrap();
+            assert_eq!(bytes, double(9007199254740992.0));
+            assert_eq!(super::deserialize(&bytes), Ok(1u64 << 53));
+            let mut deserializer = ByteDeserializer::from_bytes(&bytes).strict_integers(true);
+            assert_eq!(
+                u64::deserialize(&mut deserializer),
+                Err(Error {
+                    kind: ErrorKind::InexactDouble
+                })
+            );
+        }
+        let min = double(-9007199254740992.0);
+        let mut deserializer = ByteDeserializer::from_bytes(&min).strict_integers(true);
+        assert!(i64::deserialize(&mut deserializer).is_err());
+    }
+
+    #[test]
+    fn test_expect_marker() {
+        assert_eq!(
+            super::deserialize_expecting::<Vec<u32>>(b"\x04\x05", super::Marker::Array),
+            Err(super::Error {
+                kind: super::ErrorKind::UnexpectedMarker {
+                    expected: super::Marker::Array,
+                    found: super::Marker::Integer,
+                }
+            })
+        );
+        assert_eq!(
+            super::deserialize_expecting::<u32>(b"\x04\x05", super::Marker::Integer),
+            Ok(5)
+        );
+    }
+
+    #[derive(Deserialize, Debug, Clone, PartialEq)]
+    struct Test {
+        a: u32,
+        b: u32,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Outer {
+        inner: Test,
+    }
+
+    #[test]
+    fn test_reversed_members() {
+        // dynamic members `b` and `a`
+        let input = b"\x0A\x0B\x01\x03b\x04\x02\x03a\x04\x01\x01";
+        assert_eq!(super::deserialize(input), Ok(Test { a: 1, b: 2 }));
+        // sealed members `b` and `a`
+        let input = b"\x0A\x23\x01\x03b\x03a\x04\x02\x04\x01";
+        assert_eq!(super::deserialize(input), Ok(Test { a: 1, b: 2 }));
+    }
+
+    #[test]
+    fn test_dictionary_pairs() {
+        // {{a: 1, b: 2}: 7, {a: 3, b: 4}: 8}, the second key uses the traits of the first
+        let dictionary = b"\x11\x05\x00\x0A\x23\x01\x03a\x03b\x04\x01\x04\x02\x04\x07\
+            \x0A\x01\x04\x03\x04\x04\x04\x08";
+        let expected = vec![(Test { a: 1, b: 2 }, 7), (Test { a: 3, b: 4 }, 8)];
+        assert_eq!(
+            super::deserialize::<Vec<(Test, u32)>>(dictionary),
+            Ok(expected.clone())
+        );
+
+        // the same dictionary again by reference
+        let mut input = b"\x09\x05\x01".to_vec();
+        input.extend_from_slice(dictionary);
+        input.extend_from_slice(b"\x11\x02");
+        assert_eq!(
+            super::deserialize::<Vec<Vec<(Test, u32)>>>(&input),
+            Ok(vec![expected.clone(), expected])
+        );
     }
 
-    fn deserialize_newtype_struct<V>(
-        self,
-        _name: &'static str,
-        _visitor: V,
-    ) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        todo!()
+    #[test]
+    fn test_deserialize_prefix() {
+        let input = b"\x06\x0BHello\xDE\xAD\xBE\xEF";
+        assert_eq!(
+            super::deserialize_prefix::<&str>(input),
+            Ok(("Hello", &b"\xDE\xAD\xBE\xEF"[..]))
+        );
+        assert_eq!(
+            super::deserialize_prefix::<u32>(b"\x04\x05"),
+            Ok((5, &[][..]))
+        );
+        assert!(super::deserialize_prefix::<&str>(b"\x06\x0BHell").is_err());
     }
 
-    fn deserialize_tuple_struct<V>(
-        self,
-        _name: &'static str,
-        _len: usize,
-        _visitor: V,
-    ) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        todo!()
+    #[test]
+    fn test_deserialize_located() {
+        // {inner: {a: 1, b: <truncated integer>}}
+        let input = b"\x0A\x0B\x01\x0Binner\x0A\x0B\x01\x03a\x04\x01\x03b\x04\x81";
+        assert_eq!(
+            super::deserialize_located::<Outer>(input),
+            Err((EOS_ERROR, input.len()))
+        );
+        // an unknown marker after the first member
+        let input = b"\x0A\x0B\x01\x03a\x04\x01\x03b\x12";
+        let (error, offset) = super::deserialize_located::<Test>(input).unwrap_err();
+        assert_eq!(error.invalid_marker_byte(), Some(0x12));
+        assert_eq!(offset, input.len());
+        assert_eq!(super::deserialize_located(b"\x04\x05"), Ok(5));
     }
 
-    fn deserialize_enum<V>(
-        self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
-        _visitor: V,
-    ) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        todo!()
+    #[test]
+    fn test_expect_double() {
+        let input = [0x05, 0, 0, 0, 0, 0, 0, 0xD0, 0x3F, 0x04, 0x05];
+        let mut deserializer = ByteDeserializer::from_bytes(&input);
+        assert_eq!(deserializer.expect_double(), Ok(0.25));
+        assert_eq!(
+            deserializer.expect_double(),
+            Err(Error {
+                kind: ErrorKind::UnexpectedMarker {
+                    expected: super::Marker::Double,
+                    found: super::Marker::Integer
+                }
+            })
+        );
+        // the integer is still there
+        assert_eq!(u32::deserialize(&mut deserializer), Ok(5));
     }
 
-    /*fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        todo!()
-    }*/
+    #[test]
+    fn test_clear_reference_tables() {
+        // the second string refers to the first
+        let input = b"\x06\x0BHello\x06\x00";
+        let mut deserializer = super::ByteDeserializer::from_bytes(input);
+        assert_eq!(<&str>::deserialize(&mut deserializer), Ok("Hello"));
+        assert_eq!(<&str>::deserialize(&mut deserializer), Ok("Hello"));
 
-    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.inner.skip()?;
-        visitor.visit_none()
+        let mut deserializer = super::ByteDeserializer::from_bytes(input);
+        assert_eq!(<&str>::deserialize(&mut deserializer), Ok("Hello"));
+        deserializer.clear_reference_tables();
+        assert_eq!(deserializer.position(), 7);
+        assert_eq!(
+            <&str>::deserialize(&mut deserializer),
+            Err(Error {
+                kind: ErrorKind::Format(format::Error::MissingStringReference)
+            })
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use serde::Deserialize;
+    #[test]
+    fn test_position() {
+        let input = b"\x04\x05\x06\x0BHello\x03";
+        let mut deserializer = super::ByteDeserializer::from_bytes(input);
+        assert_eq!(deserializer.position(), 0);
+        assert_eq!(u32::deserialize(&mut deserializer), Ok(5));
+        assert_eq!(deserializer.position(), 2);
+        assert_eq!(<&str>::deserialize(&mut deserializer), Ok("Hello"));
+        assert_eq!(deserializer.position(), 9);
+        assert_eq!(bool::deserialize(&mut deserializer), Ok(true));
+        assert_eq!(deserializer.position(), input.len());
+    }
 
-    use super::{format, Error, ErrorKind};
+    #[test]
+    fn test_array() {
+        assert_eq!(
+            super::deserialize::<Vec<u32>>(&[0x09, 0x7, 0x01, 0x04, 1, 0x04, 2, 0x04, 3]),
+            Ok(vec![1, 2, 3])
+        );
+        assert_eq!(
+            super::deserialize(&[0x09, 0x1, 0x03, b'a', 0x04, 5, 0x03, b'b', 0x04, 7, 0x01]),
+            Ok(Test { a: 5, b: 7 })
+        );
+    }
 
-    const EOS_ERROR: Error = Error {
-        kind: ErrorKind::Format(format::Error::EndOfStream),
-    };
+    #[test]
+    fn test_smart_pointers() {
+        use std::{rc::Rc, sync::Arc};
+
+        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(Box::new(5u32)));
+        let input = [0x0A, 0x23, 0x01, 0x03, b'a', 0x03, b'b', 0x04, 5, 0x04, 7];
+        assert_eq!(super::deserialize(&input), Ok(Rc::new(Test { a: 5, b: 7 })));
+        let input = [0x09, 0x05, 0x01, 0x04, 1, 0x04, 2];
+        assert_eq!(super::deserialize(&input), Ok(Arc::new(vec![1u32, 2])));
+        // each reference to the same value is decoded into its own pointer
+        let input = [0x09, 0x05, 0x01, 0x09, 0x03, 0x01, 0x04, 1, 0x09, 0x02];
+        let values = super::deserialize::<Vec<Arc<Vec<u32>>>>(&input).unwrap();
+        assert_eq!(values, [Arc::new(vec![1]), Arc::new(vec![1])]);
+    }
 
     #[test]
-    fn test_bool() {
-        assert_eq!(super::deserialize::<bool>(&[]), Err(EOS_ERROR));
-        assert_eq!(super::deserialize::<bool>(&[0x02]), Ok(false));
-        assert_eq!(super::deserialize::<bool>(&[0x03]), Ok(true));
+    fn test_associative_struct() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Player<'a> {
+            name: &'a str,
+            level: u32,
+            online: bool,
+            score: f64,
+        }
+
+        // the keys are in a different order than the fields, with an unknown one
+        let input = b"\x09\x01\x0Bscore\x05\0\0\0\0\0\0\x04\x40\x0Donline\x03\
+            \x09rank\x04\x03\x09name\x06\x07Bob\x0Blevel\x04\x07\x01";
+        assert_eq!(
+            super::deserialize(input),
+            Ok(Player {
+                name: "Bob",
+                level: 7,
+                online: true,
+                score: 2.5,
+            })
+        );
     }
 
     #[test]
-    fn test_integer() {
-        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(5u8));
-        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(5u16));
-        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(5u32));
-        assert_eq!(super::deserialize(&[0x04, 0x05]), Ok(5u64));
+    fn test_associative_key_reference() {
+        // the first key of the second array refers to the string "a"
+        let input = [
+            0x09, 0x05, 0x01, 0x09, 0x01, 0x03, b'a', 0x04, 1, 0x01, 0x09, 0x01, 0x00, 0x04, 2,
+            0x01,
+        ];
+        assert_eq!(
+            super::deserialize(&input),
+            Ok(vec![
+                std::collections::BTreeMap::from([("a", 1u32)]),
+                std::collections::BTreeMap::from([("a", 2)]),
+            ])
+        );
     }
 
     #[test]
-    fn test_double() {
-        assert_eq!(super::deserialize(&[0x05, 0, 0, 0, 0, 0, 0, 0, 0]), Ok(0.0));
+    fn test_integer_member_names() {
+        let input = b"\x0A\x0B\x01\x030\x06\x03a\x035\x06\x03b\x01";
         assert_eq!(
-            super::deserialize(&[0x05, 0, 0, 0, 0, 0, 0, 0, 0]),
-            Ok(0.0f32)
+            super::deserialize(input),
+            Ok(std::collections::HashMap::from([
+                (0u32, String::from("a")),
+                (5, String::from("b"))
+            ]))
         );
+        let input = b"\x0A\x0B\x01\x03x\x06\x03a\x01";
+        assert!(super::deserialize::<std::collections::HashMap<u32, String>>(input).is_err());
         assert_eq!(
-            super::deserialize(&[0x05, 0, 0, 0, 0, 0, 0, 0xD0, 0x3F]),
-            Ok(0.25)
+            super::deserialize(input),
+            Ok(std::collections::HashMap::from([("x", "a")]))
         );
+    }
+
+    #[test]
+    fn test_undefined_as_absent() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Defaults {
+            #[serde(default)]
+            a: u32,
+            b: u32,
+        }
+
+        // sealed member `a` and dynamic member `b`, both undefined
+        let input = b"\x0A\x1B\x01\x03a\x00\x03b\x00\x03b\x04\x05\x01";
+        assert!(super::deserialize::<Defaults>(input).is_err());
+        let mut deserializer = ByteDeserializer::from_bytes(input).undefined_as_absent(true);
         assert_eq!(
-            super::deserialize(&[0x05, 0, 0, 0, 0, 0, 0, 0xD0, 0x3F]),
-            Ok(0.25f32)
+            Defaults::deserialize(&mut deserializer),
+            Ok(Defaults { a: 0, b: 5 })
         );
+        let input = b"\x09\x01\x03a\x00\x03b\x04\x05\x01";
+        let mut deserializer = ByteDeserializer::from_bytes(input).undefined_as_absent(true);
         assert_eq!(
-            super::deserialize(&[0x05, 0x9A, 0x99, 0x99, 0x99, 0x99, 0x99, 0xB9, 0x3F]),
-            Ok(0.1)
+            Defaults::deserialize(&mut deserializer),
+            Ok(Defaults { a: 0, b: 5 })
         );
     }
 
     #[test]
-    fn test_string() {
-        assert_eq!(super::deserialize(b"\x06\x0BHello"), Ok("Hello"));
+    fn test_object() {
+        // sealed members
+        assert_eq!(
+            super::deserialize(&[0x0A, 0x23, 0x01, 0x03, b'a', 0x03, b'b', 0x04, 5, 0x04, 7]),
+            Ok(Test { a: 5, b: 7 })
+        );
+        // dynamic members
+        assert_eq!(
+            super::deserialize(&[0x0A, 0x0B, 0x01, 0x03, b'a', 0x04, 5, 0x03, b'b', 0x04, 7, 0x01]),
+            Ok(Test { a: 5, b: 7 })
+        );
+        // sealed and dynamic members
+        assert_eq!(
+            super::deserialize(&[0x0A, 0x1B, 0x01, 0x03, b'a', 0x04, 5, 0x03, b'b', 0x04, 7, 0x01]),
+            Ok(Test { a: 5, b: 7 })
+        );
     }
 
     #[test]
-    fn test_option() {
-        assert_eq!(super::deserialize::<Option<u32>>(b"\x00"), Ok(None));
+    fn test_struct_round_trip() {
+        #[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+        struct Player {
+            name: String,
+            score: f64,
+            items: Vec<u32>,
+            position: Point,
+        }
+
+        #[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let player = Player {
+            name: String::from("Mardolf"),
+            score: 2.5,
+            items: vec![3, 4],
+            position: Point { x: -5, y: 7 },
+        };
+        let bytes = crate::serialize(&player).unwrap();
+        // an anonymous object, with the fields as dynamic members
+        assert_eq!(bytes[..3], [0x0A, 0x0B, 0x01]);
+        assert_eq!(super::deserialize(&bytes), Ok(player));
+    }
+
+    #[test]
+    fn test_adjacently_tagged() {
+        #[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+        #[serde(tag = "t", content = "c")]
+        enum Action {
+            FlyUp {
+                #[serde(rename = "Distance")]
+                distance: u32,
+            },
+            Stop,
+        }
+
+        let fly_up = Action::FlyUp { distance: 5 };
+        let input = b"\x0A\x0B\x01\x03t\x06\x0BFlyUp\x03c\x0A\x0B\x01\x11Distance\x04\x05\x01\x01";
+        assert_eq!(super::deserialize(input), Ok(fly_up));
+        // the content before the tag is buffered
+        let input = b"\x0A\x0B\x01\x03c\x0A\x0B\x01\x11Distance\x04\x05\x01\x03t\x06\x0BFlyUp\x01";
+        assert_eq!(super::deserialize(input), Ok(Action::FlyUp { distance: 5 }));
+        assert_eq!(
+            super::deserialize(b"\x0A\x0B\x01\x03t\x06\x09Stop\x01"),
+            Ok(Action::Stop)
+        );
+
+        let mut deserializer =
+            ByteDeserializer::from_bytes(b"\x0A\x0B\x01\x03t\x06\x09Stop\x01").with_stats();
+        assert_eq!(Action::deserialize(&mut deserializer), Ok(Action::Stop));
+        let stats = deserializer.into_stats().unwrap();
+        assert_eq!(stats.count(super::Marker::String), 1);
+        let error = super::deserialize::<Action>(b"\x0A\x0B\x01\x03t\x04\x01\x01").unwrap_err();
+        assert!(error.to_string().contains("a value with marker Integer"));
+
+        for action in [Action::FlyUp { distance: 7 }, Action::Stop] {
+            let bytes = crate::serialize(&action).unwrap();
+            assert_eq!(super::deserialize(&bytes), Ok(action));
+        }
+    }
+
+    #[test]
+    fn test_traits_reference() {
+        // the second object uses the traits of the first, with its own values
+        let input = [
+            0x09, 0x05, 0x01, 0x0A, 0x23, 0x01, 0x03, b'a', 0x03, b'b', 0x04, 1, 0x04, 2, 0x0A,
+            0x01, 0x04, 3, 0x04, 4,
+        ];
+        assert_eq!(
+            super::deserialize(&input),
+            Ok(vec![Test { a: 1, b: 2 }, Test { a: 3, b: 4 }])
+        );
+        // dynamic traits, the second object refers to the member name `b`
+        let input = [
+            0x09, 0x05, 0x01, 0x0A, 0x1B, 0x01, 0x03, b'a', 0x04, 1, 0x03, b'b', 0x04, 2, 0x01,
+            0x0A, 0x01, 0x04, 3, 0x02, 0x04, 4, 0x01,
+        ];
+        assert_eq!(
+            super::deserialize(&input),
+            Ok(vec![Test { a: 1, b: 2 }, Test { a: 3, b: 4 }])
+        );
+    }
+
+    const OBJECT_PROXY: &[u8] = b"\x0A\x13\x01\x0Binner\
+        \x0A\x07\x3Bflex.messaging.io.ObjectProxy\
+        \x0A\x23\x01\x03a\x03b\x04\x05\x04\x07";
+
+    #[test]
+    fn test_object_proxy() {
+        assert_eq!(
+            super::deserialize(OBJECT_PROXY),
+            Ok(Outer {
+                inner: Test { a: 5, b: 7 }
+            })
+        );
+        let mut deserializer =
+            super::ByteDeserializer::from_bytes(OBJECT_PROXY).object_proxy(false);
+        assert_eq!(
+            Outer::deserialize(&mut deserializer),
+            Err(Error {
+                kind: ErrorKind::Externalizable(String::from("flex.messaging.io.ObjectProxy"))
+            })
+        );
+    }
+
+    #[test]
+    fn test_array_collection() {
+        let input = b"\x0A\x07\x43flex.messaging.io.ArrayCollection\x09\x05\x01\x04\x01\x04\x02";
+        assert_eq!(super::deserialize(input), Ok(vec![1u32, 2]));
+        let mut deserializer = super::ByteDeserializer::from_bytes(input).array_collection(false);
+        assert!(Vec::<u32>::deserialize(&mut deserializer).is_err());
+    }
+
+    #[test]
+    fn test_unwrap_single_element() {
+        let input = [0x09, 0x03, 0x01, 0x04, 0x05];
+        let mut deserializer =
+            super::ByteDeserializer::from_bytes(&input).unwrap_single_element(true);
+        assert_eq!(u32::deserialize(&mut deserializer), Ok(5));
+        assert!(super::deserialize::<u32>(&input).is_err());
+        // sequences are still decoded as such
+        let mut deserializer =
+            super::ByteDeserializer::from_bytes(&input).unwrap_single_element(true);
+        assert_eq!(Vec::<u32>::deserialize(&mut deserializer), Ok(vec![5]));
+        let mut deserializer =
+            super::ByteDeserializer::from_bytes(&input[3..]).unwrap_single_element(true);
+        assert_eq!(Vec::<u32>::deserialize(&mut deserializer), Ok(vec![5]));
+        assert!(super::deserialize::<Vec<u32>>(&input[3..]).is_err());
+    }
+
+    #[test]
+    fn test_unwrap_single_element_reference() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Unwrapped {
+            a: u32,
+            b: Vec<u32>,
+        }
+
+        // the object has index 0, the unwrapped array has index 1
+        let input = b"\x0A\x23\x01\x03a\x03b\x09\x03\x01\x04\x05\x09\x02";
+        let mut deserializer = ByteDeserializer::from_bytes(input)
+            .unwrap_single_element(true)
+            .with_stats();
+        assert_eq!(
+            Unwrapped::deserialize(&mut deserializer),
+            Ok(Unwrapped { a: 5, b: vec![5] })
+        );
+        let stats = deserializer.into_stats().unwrap();
+        assert_eq!(stats.count(super::Marker::Array), 2);
+        assert_eq!(stats.count(super::Marker::Integer), 1);
+    }
+
+    #[test]
+    fn test_strict_class_names() {
+        // the second object refers to the class name and member name of the first
+        let input = [
+            0x09, 0x05, 0x01, 0x0A, 0x13, 0x07, b'F', b'o', b'o', 0x03, b'a', 0x04, 1, 0x0A, 0x13,
+            0x00, 0x02, 0x04, 2,
+        ];
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Foo {
+            a: u32,
+        }
+        assert_eq!(
+            super::deserialize(&input),
+            Ok(vec![Foo { a: 1 }, Foo { a: 2 }])
+        );
+        let mut deserializer = super::ByteDeserializer::from_bytes(&input).strict_class_names(true);
+        assert_eq!(
+            Vec::<Foo>::deserialize(&mut deserializer),
+            Err(Error {
+                kind: ErrorKind::Format(format::Error::ClassNameByReference)
+            })
+        );
     }
 
     #[derive(Deserialize, Debug, PartialEq)]
-    struct Test {
-        a: u32,
-        b: u32,
+    struct Pair {
+        first: Test,
+        second: Test,
     }
 
     #[test]
-    fn test_array() {
+    fn test_object_reference() {
+        let input = b"\x0A\x23\x01\x0Bfirst\x0Dsecond\
+            \x0A\x23\x01\x03a\x03b\x04\x05\x04\x07\
+            \x0A\x02";
         assert_eq!(
-            super::deserialize::<Vec<u32>>(&[0x09, 0x7, 0x01, 0x04, 1, 0x04, 2, 0x04, 3]),
-            Ok(vec![1, 2, 3])
+            super::deserialize(input),
+            Ok(Pair {
+                first: Test { a: 5, b: 7 },
+                second: Test { a: 5, b: 7 },
+            })
         );
+        // the array has index 0, the object has index 1
+        let input = b"\x09\x07\x01\x0A\x23\x01\x03a\x03b\x04\x05\x04\x07\x0A\x02\x0A\x02";
         assert_eq!(
-            super::deserialize(&[0x09, 0x1, 0x03, b'a', 0x04, 5, 0x03, b'b', 0x04, 7, 0x01]),
-            Ok(Test { a: 5, b: 7 })
+            super::deserialize(input),
+            Ok(vec![
+                Test { a: 5, b: 7 },
+                Test { a: 5, b: 7 },
+                Test { a: 5, b: 7 }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_array_reference() {
+        let input = [
+            0x09, 0x05, 0x01, 0x09, 0x05, 0x01, 0x04, 1, 0x04, 2, 0x09, 0x02,
+        ];
+        assert_eq!(
+            super::deserialize(&input),
+            Ok(vec![vec![1u32, 2], vec![1, 2]])
+        );
+        let input = [0x09, 0x05, 0x01, 0x0C, 0x05, 0xAB, 0xCD, 0x0C, 0x02];
+        assert_eq!(
+            super::deserialize(&input),
+            Ok(vec![crate::ValueRef::ByteArray(&[0xAB, 0xCD]); 2])
+        );
+    }
+
+    #[test]
+    fn test_cyclic_reference() {
+        let input = b"\x0A\x13\x01\x0Binner\x0A\x00";
+        assert_eq!(
+            super::deserialize::<Outer>(input),
+            Err(Error {
+                kind: ErrorKind::Format(format::Error::CyclicReference)
+            })
+        );
+        assert_eq!(
+            super::deserialize::<Outer>(b"\x0A\x13\x01\x0Binner\x0A\x04"),
+            Err(Error {
+                kind: ErrorKind::Format(format::Error::MissingObjectReference)
+            })
+        );
+    }
+
+    #[test]
+    fn test_is_human_readable() {
+        /// An IPv4 address, as text for readable formats and as four bytes otherwise
+        #[derive(Debug, PartialEq)]
+        struct Addr([u8; 4]);
+
+        impl<'de> Deserialize<'de> for Addr {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                use serde::de::Error;
+                if deserializer.is_human_readable() {
+                    let text = <&str>::deserialize(deserializer)?;
+                    let addr: std::net::Ipv4Addr = text.parse().map_err(D::Error::custom)?;
+                    Ok(Addr(addr.octets()))
+                } else {
+                    let bytes = <&[u8]>::deserialize(deserializer)?;
+                    let octets = bytes.try_into().map_err(D::Error::custom)?;
+                    Ok(Addr(octets))
+                }
+            }
+        }
+
+        let input = b"\x0C\x09\x7F\x00\x00\x01";
+        assert_eq!(crate::deserialize::<Addr>(input), Ok(Addr([127, 0, 0, 1])));
+        let input = b"\x06\x13127.0.0.1";
+        assert!(crate::deserialize::<Addr>(input).is_err());
+    }
+
+    /// Decodes `{a: <value>, b: <field>}` into a struct without `a`
+    fn skip_field<T: serde::de::DeserializeOwned>(value: &[u8], field: &[u8]) -> Result<T, Error> {
+        #[derive(Deserialize)]
+        struct Field<T> {
+            b: T,
+        }
+
+        let mut input = b"\x0A\x0B\x01\x03a".to_vec();
+        input.extend_from_slice(value);
+        input.extend_from_slice(b"\x03b");
+        input.extend_from_slice(field);
+        input.push(0x01);
+        super::deserialize::<Field<T>>(&input).map(|f| f.b)
+    }
+
+    #[test]
+    fn test_skip_string() {
+        // "a" and "hi" are the first two strings, `b` refers to "hi"
+        assert_eq!(
+            skip_field(b"\x06\x05hi", b"\x06\x02"),
+            Ok(String::from("hi"))
+        );
+    }
+
+    #[test]
+    fn test_skip_xml_doc() {
+        assert_eq!(skip_field(b"\x07\x09<a/>", b"\x04\x05"), Ok(5));
+    }
+
+    #[test]
+    fn test_skip_date() {
+        assert_eq!(
+            skip_field(b"\x08\x01\0\0\0\0\0\x40\x8F\x40", b"\x04\x05"),
+            Ok(5)
+        );
+    }
+
+    #[test]
+    fn test_skip_array() {
+        let array = b"\x09\x05\x03k\x04\x01\x01\x04\x02\x06\x03x";
+        assert_eq!(skip_field(array, b"\x04\x05"), Ok(5));
+        // the skipped array is the second entry in the object table, after the outer object
+        let array = b"\x09\x05\x01\x04\x02\x04\x03";
+        assert_eq!(skip_field(array, b"\x09\x02"), Ok(vec![2, 3]));
+    }
+
+    #[test]
+    fn test_skip_object() {
+        let object = b"\x0A\x13\x03C\x03x\x0A\x0B\x01\x03y\x04\x01\x01";
+        assert_eq!(skip_field(object, b"\x04\x05"), Ok(5));
+        // the traits of the skipped object can be referenced
+        let field = b"\x0A\x05\x04\x07";
+        assert_eq!(
+            skip_field(object, field),
+            Ok(std::collections::HashMap::from([(String::from("x"), 7)]))
+        );
+    }
+
+    #[test]
+    fn test_skip_externalizable() {
+        let object = b"\x0A\x07\x43flex.messaging.io.ArrayCollection\x09\x03\x01\x04\x01";
+        assert_eq!(skip_field(object, b"\x04\x05"), Ok(5));
+        let object = b"\x0A\x07\x07Foo\x04\x01";
+        assert!(skip_field::<i32>(object, b"\x04\x05").is_err());
+    }
+
+    #[test]
+    fn test_skip_xml() {
+        assert_eq!(skip_field(b"\x0B\x09<a/>", b"\x04\x05"), Ok(5));
+    }
+
+    #[test]
+    fn test_skip_byte_array() {
+        let bytes = b"\x0C\x05\xAB\xCD";
+        assert_eq!(skip_field(bytes, b"\x04\x05"), Ok(5));
+        assert_eq!(skip_field(bytes, b"\x0C\x02"), Ok(vec![0xAB, 0xCD]));
+    }
+
+    #[test]
+    fn test_skip_vector_int() {
+        let vector = b"\x0D\x05\x00\x01\0\0\0\xFF\xFF\xFF\xFF";
+        assert_eq!(skip_field(vector, b"\x04\x05"), Ok(5));
+    }
+
+    #[test]
+    fn test_skip_vector_uint() {
+        assert_eq!(skip_field(b"\x0E\x03\x01\x01\0\0\0", b"\x04\x05"), Ok(5));
+    }
+
+    #[test]
+    fn test_skip_vector_double() {
+        let vector = b"\x0F\x03\x00\0\0\0\0\0\0\xD0\x3F";
+        assert_eq!(skip_field(vector, b"\x04\x05"), Ok(5));
+    }
+
+    #[test]
+    fn test_skip_vector_object() {
+        let vector = b"\x10\x05\x00\x01\x04\x01\x06\x03y";
+        assert_eq!(skip_field(vector, b"\x04\x05"), Ok(5));
+    }
+
+    #[test]
+    fn test_skip_dictionary() {
+        let dictionary = b"\x11\x03\x00\x06\x03k\x04\x07";
+        assert_eq!(skip_field(dictionary, b"\x04\x05"), Ok(5));
+        // truncated
+        assert!(skip_field::<i32>(b"\x11\x03\x00\x06\x03k", b"").is_err());
+        // the weak keys flag is neither 0 nor 1
+        assert_eq!(
+            skip_field::<i32>(b"\x11\x03\x02\x06\x03k\x04\x07", b"\x04\x05"),
+            Err(format::Error::InvalidFlag(2).into())
         );
     }
 }
@@ -0,0 +1,243 @@
+//! Settings for decoding, see [`ByteDeserializer::with_options`](crate::ByteDeserializer::with_options)
+
+/// The visitor method that integers are passed to by `deserialize_any`
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum NumberHint {
+    /// `visit_u32`, negative integers wrap around
+    U32,
+    /// `visit_i64`
+    #[default]
+    I64,
+    /// `visit_f64`, so that integers and doubles look the same
+    F64,
+}
+
+/// The byte order of the 8 bytes of a double
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Endianness {
+    /// Least significant byte first, as written by this crate
+    #[default]
+    Little,
+    /// Most significant byte first, as in AMF0 and some related formats
+    Big,
+}
+
+/// Returns the length of the value after an unknown `marker` at the start of `input`,
+/// or `None` if the marker is not supported after all
+pub type UnknownMarkerHandler = fn(marker: u8, input: &[u8]) -> Option<usize>;
+
+/// The settings of a [`ByteDeserializer`](crate::ByteDeserializer)
+#[derive(Debug, Clone)]
+pub struct DeserializerOptions {
+    pub(crate) number_hint: NumberHint,
+    pub(crate) integral_doubles: bool,
+    pub(crate) strict_integers: bool,
+    pub(crate) char_code_points: bool,
+    pub(crate) undefined_as_absent: bool,
+    pub(crate) strict_casts: bool,
+    pub(crate) array_collection: bool,
+    pub(crate) object_proxy: bool,
+    pub(crate) unwrap_single_element: bool,
+    pub(crate) strict_class_names: bool,
+    pub(crate) disable_references: bool,
+    pub(crate) rewalk_budget: usize,
+    pub(crate) unknown_markers: Option<UnknownMarkerHandler>,
+    pub(crate) double_endianness: Endianness,
+    pub(crate) input_limit: Option<usize>,
+}
+
+impl Default for DeserializerOptions {
+    fn default() -> Self {
+        Self {
+            number_hint: NumberHint::default(),
+            integral_doubles: false,
+            strict_integers: false,
+            char_code_points: false,
+            undefined_as_absent: false,
+            strict_casts: false,
+            array_collection: true,
+            object_proxy: true,
+            unwrap_single_element: false,
+            strict_class_names: false,
+            disable_references: false,
+            rewalk_budget: 1 << 20,
+            unknown_markers: None,
+            double_endianness: Endianness::default(),
+            input_limit: None,
+        }
+    }
+}
+
+impl DeserializerOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Choose how `deserialize_any` passes integers to the visitor (default: [`NumberHint::I64`])
+    pub fn number_hint(mut self, hint: NumberHint) -> Self {
+        self.number_hint = hint;
+        self
+    }
+
+    /// Let `deserialize_any` pass doubles without a fractional part to `visit_i64` (default: `false`)
+    pub fn integral_doubles(mut self, enabled: bool) -> Self {
+        self.integral_doubles = enabled;
+        self
+    }
+
+    /// Reject doubles with a fractional part where an integer is requested,
+    /// instead of truncating them (default: `false`)
+    ///
+    /// Doubles beyond `2^53 - 1` are rejected as well, because they may have
+    /// been rounded from a different integer.
+    pub fn strict_integers(mut self, enabled: bool) -> Self {
+        self.strict_integers = enabled;
+        self
+    }
+
+    /// Reject integers that don't fit the requested integer type, instead of
+    /// wrapping around, and doubles that aren't a whole number in its range,
+    /// instead of truncating or saturating them (default: `false`)
+    pub fn strict_casts(mut self, enabled: bool) -> Self {
+        self.strict_casts = enabled;
+        self
+    }
+
+    /// Accept an integer code point where a `char` is requested, in addition
+    /// to a string with a single character (default: `false`)
+    pub fn char_code_points(mut self, enabled: bool) -> Self {
+        self.char_code_points = enabled;
+        self
+    }
+
+    /// Skip members and associative keys whose value is `Undefined`, so that
+    /// `#[serde(default)]` applies as if they were absent (default: `false`)
+    pub fn undefined_as_absent(mut self, enabled: bool) -> Self {
+        self.undefined_as_absent = enabled;
+        self
+    }
+
+    /// Decode `flex.messaging.io.ArrayCollection` as the array it wraps (default: `true`)
+    pub fn array_collection(mut self, enabled: bool) -> Self {
+        self.array_collection = enabled;
+        self
+    }
+
+    /// Decode `flex.messaging.io.ObjectProxy` as the object it wraps (default: `true`)
+    pub fn object_proxy(mut self, enabled: bool) -> Self {
+        self.object_proxy = enabled;
+        self
+    }
+
+    /// Decode an array with exactly one element as that element if a scalar is requested,
+    /// and a scalar as a sequence with one element if a sequence is requested (default: `false`)
+    pub fn unwrap_single_element(mut self, enabled: bool) -> Self {
+        self.unwrap_single_element = enabled;
+        self
+    }
+
+    /// Reject class names that are encoded as a reference to an earlier string (default: `false`)
+    pub fn strict_class_names(mut self, enabled: bool) -> Self {
+        self.strict_class_names = enabled;
+        self
+    }
+
+    /// Reject strings, traits and complex values that are encoded as a reference
+    /// to an earlier one, e.g. for untrusted input (default: `false`)
+    pub fn disable_references(mut self, enabled: bool) -> Self {
+        self.disable_references = enabled;
+        self
+    }
+
+    /// Limit how often values are decoded again to resolve object references
+    ///
+    /// References are resolved by decoding the referenced value from its position
+    /// in the input, so a chain of values that each refer to the previous one
+    /// more than once takes exponential time. Every such re-walk, including nested
+    /// ones, counts against this budget (default: `2^20`).
+    pub fn rewalk_budget(mut self, budget: usize) -> Self {
+        self.rewalk_budget = budget;
+        self
+    }
+
+    /// Stop at `limit` bytes of input, so that reading past them fails with
+    /// [`Error::is_input_limit_exceeded`](crate::Error::is_input_limit_exceeded)
+    /// instead of reading on (default: `None`)
+    ///
+    /// The bytes after the limit are not visible at all, not even through
+    /// [`remaining`](crate::FormatDeserializer::remaining).
+    pub fn input_limit(mut self, limit: Option<usize>) -> Self {
+        self.input_limit = limit;
+        self
+    }
+
+    /// Choose the byte order of doubles, including those of dates and `Vector.<Number>`
+    /// (default: [`Endianness::Little`])
+    ///
+    /// The elements of `Vector.<int>` and `Vector.<uint>` are always read little-endian.
+    pub fn double_endianness(mut self, endianness: Endianness) -> Self {
+        self.double_endianness = endianness;
+        self
+    }
+
+    /// Skip values with a marker beyond [`Marker::Dictionary`](crate::Marker::Dictionary),
+    /// e.g. from an extension of the format, and decode them as `Undefined`
+    ///
+    /// The `handler` is called with the marker and the input after it, and returns how many
+    /// bytes to skip. Without a handler, or if it returns `None`, such a marker is an
+    /// error (default: `None`).
+    pub fn unknown_markers(mut self, handler: Option<UnknownMarkerHandler>) -> Self {
+        self.unknown_markers = handler;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::{DeserializerOptions, Endianness, NumberHint};
+    use crate::ByteDeserializer;
+
+    #[test]
+    fn test_options() {
+        // [-1, 2.0, [3]]
+        let input = [
+            0x09, 0x07, 0x01, 0x04, 0xFF, 0xFF, 0xFF, 0xFF, 0x05, 0, 0, 0, 0, 0, 0, 0, 0x40, 0x09,
+            0x03, 0x01, 0x04, 0x03,
+        ];
+        let options = DeserializerOptions::new()
+            .number_hint(NumberHint::F64)
+            .integral_doubles(true)
+            .unwrap_single_element(true);
+        let mut deserializer = ByteDeserializer::with_options(&input, options);
+        let value = serde_json::Value::deserialize(&mut deserializer).unwrap();
+        assert_eq!(value.to_string(), "[-1.0,2,[3.0]]");
+
+        let options = DeserializerOptions::new()
+            .strict_integers(true)
+            .unwrap_single_element(true);
+        let mut deserializer = ByteDeserializer::with_options(&input, options);
+        assert_eq!(
+            <(i32, u32, u32)>::deserialize(&mut deserializer),
+            Ok((-1, 2, 3))
+        );
+    }
+
+    #[test]
+    fn test_double_endianness() {
+        let input = [0x05, 0x3F, 0xD0, 0, 0, 0, 0, 0, 0];
+        let options = DeserializerOptions::new().double_endianness(Endianness::Big);
+        let mut deserializer = ByteDeserializer::with_options(&input, options);
+        assert_eq!(f64::deserialize(&mut deserializer), Ok(0.25));
+
+        let input = [0x05, 0, 0, 0, 0, 0, 0, 0xD0, 0x3F];
+        assert_eq!(crate::deserialize::<f64>(&input), Ok(0.25));
+
+        // Vector.<int> [1, -2] is not affected
+        let input = b"\x0D\x05\x00\x01\0\0\0\xFE\xFF\xFF\xFF";
+        let options = DeserializerOptions::new().double_endianness(Endianness::Big);
+        let mut deserializer = ByteDeserializer::with_options(input, options);
+        assert_eq!(Vec::<i32>::deserialize(&mut deserializer), Ok(vec![1, -2]));
+    }
+}
@@ -0,0 +1,906 @@
+use serde::{ser::Impossible, Serialize};
+
+use super::{format, format::Marker, value, Error, ErrorKind};
+
+/// Smallest value that can be written with [`Marker::Integer`]
+const INTEGER_MIN: i64 = -(1 << 28);
+/// Largest value that can be written with [`Marker::Integer`]
+const INTEGER_MAX: i64 = (1 << 28) - 1;
+
+/// The number of distinct byte arrays that are remembered for references
+const BYTE_ARRAY_CACHE_SIZE: usize = 16;
+
+const UNIMPLEMENTED: Error = Error {
+    kind: ErrorKind::Unimplemented,
+};
+
+pub struct ByteSerializer {
+    inner: format::Serializer,
+    /// The marker for the next value of a private newtype, instead of the usual one
+    ///
+    /// This is `Array` for a map with only associative keys, and `Xml` or `XmlDoc`
+    /// for a string.
+    pending_marker: Option<Marker>,
+    /// Spare buffers for the items of sequences with an unknown length
+    ///
+    /// The dense count is written before the items, so these are buffered first.
+    /// The buffers are kept to reuse their allocation for the next sequence.
+    scratch: Vec<Vec<u8>>,
+    /// The number of values written so far that are part of the object reference table
+    objects: usize,
+    byte_array_references: bool,
+    /// Recently written byte arrays with their index in the object reference table
+    byte_arrays: Vec<(Vec<u8>, usize)>,
+    /// The weak keys flag of the next `Dictionary`
+    weak_keys: bool,
+    reject_non_finite: bool,
+}
+
+impl ByteSerializer {
+    pub fn new() -> Self {
+        Self {
+            inner: format::Serializer::new(),
+            pending_marker: None,
+            scratch: Vec::new(),
+            objects: 0,
+            byte_array_references: false,
+            byte_arrays: Vec::new(),
+            weak_keys: false,
+            reject_non_finite: false,
+        }
+    }
+
+    /// Write a byte array that is equal to a recent one as a reference to it
+    ///
+    /// Only the last few distinct byte arrays are compared.
+    pub fn byte_array_references(mut self, enabled: bool) -> Self {
+        self.byte_array_references = enabled;
+        self
+    }
+
+    /// Fail on NaN and infinite doubles, instead of writing their bit pattern,
+    /// for readers that can't handle them
+    pub fn reject_non_finite(mut self, enabled: bool) -> Self {
+        self.reject_non_finite = enabled;
+        self
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.inner.into_inner()
+    }
+
+    fn serialize_integer(&mut self, v: i64) -> Result<(), Error> {
+        if (INTEGER_MIN..=INTEGER_MAX).contains(&v) {
+            self.inner.write_marker(Marker::Integer);
+            self.inner.write_u29(v as u32 & 0x1FFFFFFF)?;
+        } else {
+            self.inner.write_marker(Marker::Double);
+            self.inner.write_double(v as f64);
+        }
+        Ok(())
+    }
+
+    /// Writes the marker of a value that is added to the object reference table
+    fn write_object_marker(&mut self, marker: Marker) {
+        self.objects += 1;
+        self.inner.write_marker(marker);
+    }
+
+    /// Starts an anonymous object, whose members are written as dynamic members
+    fn serialize_dynamic_object(&mut self) -> Result<&mut Self, Error> {
+        if self.pending_marker.take() == Some(Marker::Array) {
+            self.write_object_marker(Marker::Array);
+            // no dense entries, the associative keys end like dynamic members
+            self.inner.write_inline_header(0)?;
+            return Ok(self);
+        }
+        self.write_object_marker(Marker::Object);
+        // inline object, inline traits, not externalizable, dynamic, no sealed members
+        self.inner.write_u29(0b1011)?;
+        // anonymous class
+        self.inner.write_string("")?;
+        Ok(self)
+    }
+
+    fn serialize_dense_array(&mut self, len: usize) -> Result<&mut Self, Error> {
+        self.write_object_marker(Marker::Array);
+        self.write_dense_header(len)?;
+        Ok(self)
+    }
+
+    /// Writes the header of an array with only dense items, after the marker
+    fn write_dense_header(&mut self, len: usize) -> Result<(), Error> {
+        self.inner.write_inline_header(len)?;
+        // no associative keys
+        self.inner.write_string("")?;
+        Ok(())
+    }
+}
+
+impl Default for ByteSerializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut serializer = ByteSerializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+impl<'a> serde::Serializer for &'a mut ByteSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = ByteSerializerSeq<'a>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = ByteSerializerMap<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.inner
+            .write_marker(if v { Marker::True } else { Marker::False });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_integer(v),
+            Err(_) => {
+                self.inner.write_marker(Marker::Double);
+                self.inner.write_double(v as f64);
+                Ok(())
+            }
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        if self.reject_non_finite && !v.is_finite() {
+            return Err(Error {
+                kind: ErrorKind::NonFiniteDouble,
+            });
+        }
+        if self.pending_marker.take() == Some(Marker::Date) {
+            // always inline, the serializer doesn't reference dates
+            self.write_object_marker(Marker::Date);
+            self.inner.write_inline_header(0)?;
+        } else {
+            self.inner.write_marker(Marker::Double);
+        }
+        self.inner.write_double(v);
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        match self.pending_marker.take() {
+            // always inline, the serializer doesn't reference XML
+            Some(marker @ (Marker::Xml | Marker::XmlDoc)) => self.write_object_marker(marker),
+            _ => self.inner.write_marker(Marker::String),
+        }
+        self.inner.write_string(v)?;
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        if self.byte_array_references {
+            if let Some(&(_, index)) = self.byte_arrays.iter().find(|(bytes, _)| bytes == v) {
+                self.inner.write_marker(Marker::ByteArray);
+                self.inner.write_reference_header(index)?;
+                return Ok(());
+            }
+            if self.byte_arrays.len() == BYTE_ARRAY_CACHE_SIZE {
+                self.byte_arrays.remove(0);
+            }
+            self.byte_arrays.push((v.to_vec(), self.objects));
+        }
+        self.write_object_marker(Marker::ByteArray);
+        self.inner.write_inline_header(v.len())?;
+        self.inner.write_bytes(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.write_marker(Marker::Null);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        if name == value::UNDEFINED_TOKEN {
+            self.inner.write_marker(Marker::Undefined);
+            Ok(())
+        } else {
+            self.serialize_unit()
+        }
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        // by name, like the tag of an adjacently tagged enum
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.weak_keys = name == value::WEAK_DICTIONARY_TOKEN;
+        self.pending_marker = match name {
+            value::ASSOC_ARRAY_TOKEN => Some(Marker::Array),
+            value::DICTIONARY_TOKEN | value::WEAK_DICTIONARY_TOKEN => Some(Marker::Dictionary),
+            value::DATE_TOKEN => Some(Marker::Date),
+            value::XML_TOKEN => Some(Marker::Xml),
+            value::XML_DOC_TOKEN => Some(Marker::XmlDoc),
+            _ => None,
+        };
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(UNIMPLEMENTED)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        let buffer = match len {
+            Some(len) => {
+                self.serialize_dense_array(len)?;
+                None
+            }
+            None => {
+                // the array comes before its items in the object reference table
+                self.objects += 1;
+                let scratch = self.scratch.pop().unwrap_or_default();
+                Some((self.inner.replace_output(scratch), 0))
+            }
+        };
+        Ok(ByteSerializerSeq {
+            inner: self,
+            buffer,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_dense_array(len)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_dense_array(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(UNIMPLEMENTED)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        if self.pending_marker == Some(Marker::Dictionary) {
+            self.pending_marker = None;
+            // the entry count comes first, and keys may be any value
+            let len = len.ok_or(UNIMPLEMENTED)?;
+            self.write_object_marker(Marker::Dictionary);
+            self.inner.write_inline_header(len)?;
+            self.inner.write_byte(self.weak_keys.into());
+            return Ok(ByteSerializerMap {
+                inner: self,
+                dictionary: true,
+            });
+        }
+        Ok(ByteSerializerMap {
+            inner: self.serialize_dynamic_object()?,
+            dictionary: false,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_dynamic_object()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(UNIMPLEMENTED)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+pub struct ByteSerializerSeq<'a> {
+    inner: &'a mut ByteSerializer,
+    /// The output before the sequence and the number of items, if the length was unknown
+    buffer: Option<(Vec<u8>, usize)>,
+}
+
+impl<'a> serde::ser::SerializeSeq for ByteSerializerSeq<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        if let Some((_, len)) = &mut self.buffer {
+            *len += 1;
+        }
+        value.serialize(&mut *self.inner)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        if let Some((output, len)) = self.buffer {
+            let mut items = self.inner.inner.replace_output(output);
+            self.inner.inner.write_marker(Marker::Array);
+            self.inner.write_dense_header(len)?;
+            self.inner.inner.write_bytes(&items);
+            items.clear();
+            self.inner.scratch.push(items);
+        }
+        Ok(())
+    }
+}
+
+impl serde::ser::SerializeTuple for &mut ByteSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+impl serde::ser::SerializeTupleStruct for &mut ByteSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+pub struct ByteSerializerMap<'a> {
+    inner: &'a mut ByteSerializer,
+    /// The entries of a `Dictionary`, instead of the dynamic members of an object
+    dictionary: bool,
+}
+
+impl<'a> serde::ser::SerializeMap for ByteSerializerMap<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        if self.dictionary {
+            key.serialize(&mut *self.inner)
+        } else {
+            key.serialize(KeySerializer {
+                inner: &mut *self.inner,
+            })
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        value.serialize(&mut *self.inner)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        if !self.dictionary {
+            // end of dynamic members
+            self.inner.inner.write_string("")?;
+        }
+        Ok(())
+    }
+}
+
+impl serde::ser::SerializeStruct for &mut ByteSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        key.serialize(KeySerializer { inner: self })?;
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        // end of dynamic members
+        self.inner.write_string("")?;
+        Ok(())
+    }
+}
+
+const KEY_MUST_BE_A_STRING: Error = Error {
+    kind: ErrorKind::KeyMustBeAString,
+};
+
+/// Writes the name of a dynamic member, which is a non-empty string without a marker
+struct KeySerializer<'a> {
+    inner: &'a mut ByteSerializer,
+}
+
+impl<'a> KeySerializer<'a> {
+    fn serialize_key(self, key: &str) -> Result<(), Error> {
+        if key.is_empty() {
+            // the empty string marks the end of the dynamic members
+            Err(KEY_MUST_BE_A_STRING)
+        } else {
+            self.inner.inner.write_string(key)?;
+            Ok(())
+        }
+    }
+}
+
+impl<'a> serde::Serializer for KeySerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(&v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(&v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(&v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(&v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(&v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(&v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(&v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(&v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(v)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<Self::Ok, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_key(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(KEY_MUST_BE_A_STRING)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Pair(u32, u32);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Test {
+        a: u32,
+        b: String,
+    }
+
+    #[test]
+    fn test_bool() {
+        assert_eq!(super::serialize(&true), Ok(vec![0x03]));
+        assert_eq!(super::serialize(&false), Ok(vec![0x02]));
+        for value in [true, false] {
+            let bytes = super::serialize(&value).unwrap();
+            assert_eq!(crate::deserialize::<bool>(&bytes), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_reject_non_finite() {
+        let mut expected = vec![0x05];
+        expected.extend_from_slice(&f64::NAN.to_le_bytes());
+        assert_eq!(super::serialize(&f64::NAN), Ok(expected));
+
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut serializer = super::ByteSerializer::new().reject_non_finite(true);
+            assert_eq!(
+                value.serialize(&mut serializer),
+                Err(crate::Error {
+                    kind: crate::ErrorKind::NonFiniteDouble
+                })
+            );
+        }
+        let mut serializer = super::ByteSerializer::new().reject_non_finite(true);
+        assert_eq!(1.5f64.serialize(&mut serializer), Ok(()));
+    }
+
+    #[test]
+    fn test_integer() {
+        assert_eq!(super::serialize(&5u32), Ok(vec![0x04, 0x05]));
+        assert_eq!(
+            super::serialize(&-1i8),
+            Ok(vec![0x04, 0xFF, 0xFF, 0xFF, 0xFF])
+        );
+        // the limits of a signed 29-bit integer
+        assert_eq!(
+            super::serialize(&((1 << 28) - 1)),
+            Ok(vec![0x04, 0xBF, 0xFF, 0xFF, 0xFF])
+        );
+        assert_eq!(
+            super::serialize(&-(1 << 28)),
+            Ok(vec![0x04, 0xC0, 0x80, 0x80, 0x00])
+        );
+        for value in [1i64 << 28, -(1 << 28) - 1, 1 << 30] {
+            let mut expected = vec![0x05];
+            expected.extend_from_slice(&(value as f64).to_le_bytes());
+            assert_eq!(super::serialize(&value), Ok(expected));
+        }
+        assert_eq!(
+            crate::deserialize(&super::serialize(&(1u32 << 30)).unwrap()),
+            Ok(1u32 << 30)
+        );
+    }
+
+    #[test]
+    fn test_str() {
+        assert_eq!(super::serialize("Hello"), Ok(b"\x06\x0BHello".to_vec()));
+        assert_eq!(super::serialize(""), Ok(vec![0x06, 0x01]));
+        assert_eq!(super::serialize(&'é'), Ok(vec![0x06, 0x05, 0xC3, 0xA9]));
+    }
+
+    #[test]
+    fn test_cow() {
+        use std::borrow::Cow;
+
+        let borrowed: Cow<str> = Cow::Borrowed("Hello");
+        let owned: Cow<str> = Cow::Owned(String::from("Hello"));
+        assert_eq!(super::serialize(&borrowed), Ok(b"\x06\x0BHello".to_vec()));
+        assert_eq!(super::serialize(&borrowed), super::serialize(&owned));
+
+        // serde writes `[u8]` as a sequence, `serialize_bytes` makes it a ByteArray
+        struct Bytes<'a>(Cow<'a, [u8]>);
+
+        impl<'a> Serialize for Bytes<'a> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(&self.0)
+            }
+        }
+
+        let borrowed = Cow::Borrowed(&b"\xAB\xCD"[..]);
+        let owned: Cow<[u8]> = Cow::Owned(vec![0xAB, 0xCD]);
+        assert_eq!(
+            super::serialize(&borrowed),
+            Ok(vec![0x09, 0x05, 0x01, 0x04, 0x81, 0x2B, 0x04, 0x81, 0x4D])
+        );
+        assert_eq!(super::serialize(&borrowed), super::serialize(&owned));
+        assert_eq!(
+            super::serialize(&Bytes(borrowed)),
+            Ok(vec![0x0C, 0x05, 0xAB, 0xCD])
+        );
+        assert_eq!(
+            super::serialize(&Bytes(owned)),
+            Ok(vec![0x0C, 0x05, 0xAB, 0xCD])
+        );
+    }
+
+    #[test]
+    fn test_tuple() {
+        let bytes = super::serialize(&(5u32, String::from("Hello"))).unwrap();
+        assert_eq!(bytes, b"\x09\x05\x01\x04\x05\x06\x0BHello");
+        assert_eq!(
+            crate::deserialize::<(u32, String)>(&bytes),
+            Ok((5, String::from("Hello")))
+        );
+    }
+
+    #[test]
+    fn test_tuple_struct() {
+        let bytes = super::serialize(&Pair(1, 2)).unwrap();
+        assert_eq!(bytes, [0x09, 0x05, 0x01, 0x04, 1, 0x04, 2]);
+        assert_eq!(crate::deserialize::<Pair>(&bytes), Ok(Pair(1, 2)));
+    }
+
+    #[test]
+    fn test_struct() {
+        let value = Test {
+            a: 5,
+            b: String::from("Hello"),
+        };
+        let bytes = super::serialize(&value).unwrap();
+        assert_eq!(bytes, b"\x0A\x0B\x01\x03a\x04\x05\x03b\x06\x0BHello\x01");
+        assert_eq!(crate::deserialize::<Test>(&bytes), Ok(value));
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Optional {
+        a: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        b: Option<u32>,
+    }
+
+    #[test]
+    fn test_skipped_field() {
+        // dynamic members have no count, so skipped fields are simply not written
+        let value = Optional { a: 5, b: None };
+        let bytes = super::serialize(&value).unwrap();
+        assert_eq!(bytes, b"\x0A\x0B\x01\x03a\x04\x05\x01");
+        assert_eq!(
+            crate::deserialize(&bytes),
+            Ok(std::collections::BTreeMap::from([("a", 5u32)]))
+        );
+        assert_eq!(crate::deserialize::<Optional>(&bytes), Ok(value));
+        let value = Optional { a: 5, b: Some(7) };
+        let bytes = super::serialize(&value).unwrap();
+        assert_eq!(crate::deserialize::<Optional>(&bytes), Ok(value));
+    }
+
+    #[test]
+    fn test_map() {
+        let map = std::collections::BTreeMap::from([(1u32, true), (2, false)]);
+        let bytes = super::serialize(&map).unwrap();
+        assert_eq!(bytes, b"\x0A\x0B\x01\x031\x03\x032\x02\x01");
+        let map = std::collections::BTreeMap::from([("", true)]);
+        assert_eq!(
+            super::serialize(&map),
+            Err(crate::Error {
+                kind: crate::ErrorKind::KeyMustBeAString
+            })
+        );
+    }
+
+    #[test]
+    fn test_ipv4_addr() {
+        let addr = std::net::Ipv4Addr::new(127, 0, 0, 1);
+        let bytes = super::serialize(&addr).unwrap();
+        assert_eq!(
+            bytes,
+            [0x09, 0x09, 0x01, 0x04, 127, 0x04, 0, 0x04, 0, 0x04, 1]
+        );
+        assert_eq!(crate::deserialize::<std::net::Ipv4Addr>(&bytes), Ok(addr));
+    }
+
+    #[test]
+    fn test_unknown_length() {
+        struct Evens(u32);
+
+        impl Serialize for Evens {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq((0..self.0).filter(|i| i % 2 == 0))
+            }
+        }
+
+        let bytes = super::serialize(&Evens(5)).unwrap();
+        assert_eq!(bytes, [0x09, 0x07, 0x01, 0x04, 0, 0x04, 2, 0x04, 4]);
+        // nested sequences each count their own items
+        let bytes = super::serialize(&[Evens(3), Evens(0)]).unwrap();
+        assert_eq!(
+            bytes,
+            [0x09, 0x05, 0x01, 0x09, 0x05, 0x01, 0x04, 0, 0x04, 2, 0x09, 0x01, 0x01]
+        );
+        assert_eq!(
+            crate::deserialize::<Vec<Vec<u32>>>(&bytes),
+            Ok(vec![vec![0, 2], vec![]])
+        );
+    }
+
+    #[test]
+    fn test_byte_array_references() {
+        let value = crate::Value::ByteArray(vec![0xAB, 0xCD]);
+        let values = (&value, vec![&value]);
+        let mut serializer = super::ByteSerializer::new().byte_array_references(true);
+        values.serialize(&mut serializer).unwrap();
+        let bytes = serializer.into_inner();
+        // the outer array has index 0, the byte array 1 and the inner array 2
+        assert_eq!(
+            bytes,
+            [0x09, 0x05, 0x01, 0x0C, 0x05, 0xAB, 0xCD, 0x09, 0x03, 0x01, 0x0C, 0x02]
+        );
+        assert_eq!(
+            crate::deserialize(&bytes),
+            Ok((value.clone(), vec![value.clone()]))
+        );
+        assert_eq!(
+            super::serialize(&values).unwrap(),
+            [0x09, 0x05, 0x01, 0x0C, 0x05, 0xAB, 0xCD, 0x09, 0x03, 0x01, 0x0C, 0x05, 0xAB, 0xCD]
+        );
+    }
+}
@@ -1,15 +1,25 @@
 use super::Error;
 
 pub(super) trait VisitInt {
-    fn visit_int<'de, V: serde::de::Visitor<'de>>(visitor: V, v: u32) -> Result<V::Value, Error>;
+    /// Whether `v` is in the range of the type, instead of wrapping around
+    fn fits(v: i32) -> bool;
+
+    fn visit_int<'de, V: serde::de::Visitor<'de>>(visitor: V, v: i32) -> Result<V::Value, Error>;
 }
 
 macro_rules! impl_visit_int {
     ($f:ident $t:ty) => {
+        impl_visit_int!($f $t, |v| <$t>::try_from(v).is_ok());
+    };
+    ($f:ident $t:ty, $fits:expr) => {
         impl VisitInt for $t {
+            fn fits(v: i32) -> bool {
+                $fits(v)
+            }
+
             fn visit_int<'de, V: serde::de::Visitor<'de>>(
                 visitor: V,
-                v: u32,
+                v: i32,
             ) -> Result<V::Value, Error> {
                 visitor.$f(v as $t)
             }
@@ -25,17 +35,39 @@ impl_visit_int!(visit_i8 i8);
 impl_visit_int!(visit_i16 i16);
 impl_visit_int!(visit_i32 i32);
 impl_visit_int!(visit_i64 i64);
-impl_visit_int!(visit_f32 f32);
-impl_visit_int!(visit_f64 f64);
+// every `i29` is exact as a double, and only rounded as a float
+impl_visit_int!(visit_f32 f32, |_| true);
+impl_visit_int!(visit_f64 f64, |_| true);
 
 pub(super) trait VisitDouble {
+    /// Whether the visitor expects an integer
+    const INTEGER: bool;
+
+    /// Whether `v` is a whole number in the range of the type, instead of being truncated
+    fn fits(v: f64) -> bool;
+
     fn visit_double<'de, V: serde::de::Visitor<'de>>(visitor: V, v: f64)
         -> Result<V::Value, Error>;
 }
 
 macro_rules! impl_visit_double {
-    ($f:ident $t:ty) => {
+    ($f:ident $t:ty, false) => {
+        impl_visit_double!($f $t, false, |_| true);
+    };
+    ($f:ident $t:ty, true) => {
+        // `MAX + 1` is a power of two, so it is exact even where `MAX` is not
+        impl_visit_double!($f $t, true, |v: f64| v.fract() == 0.0
+            && v >= <$t>::MIN as f64
+            && v < <$t>::MAX as f64 + 1.0);
+    };
+    ($f:ident $t:ty, $integer:literal, $fits:expr) => {
         impl VisitDouble for $t {
+            const INTEGER: bool = $integer;
+
+            fn fits(v: f64) -> bool {
+                $fits(v)
+            }
+
             fn visit_double<'de, V: serde::de::Visitor<'de>>(
                 visitor: V,
                 v: f64,
@@ -46,13 +78,36 @@ macro_rules! impl_visit_double {
     };
 }
 
-impl_visit_double!(visit_f32 f32);
-impl_visit_double!(visit_f64 f64);
-impl_visit_double!(visit_i8 i8);
-impl_visit_double!(visit_u8 u8);
-impl_visit_double!(visit_i16 i16);
-impl_visit_double!(visit_u16 u16);
-impl_visit_double!(visit_i32 i32);
-impl_visit_double!(visit_u32 u32);
-impl_visit_double!(visit_i64 i64);
-impl_visit_double!(visit_u64 u64);
+impl_visit_double!(visit_f32 f32, false);
+impl_visit_double!(visit_f64 f64, false);
+impl_visit_double!(visit_i8 i8, true);
+impl_visit_double!(visit_u8 u8, true);
+impl_visit_double!(visit_i16 i16, true);
+impl_visit_double!(visit_u16 u16, true);
+impl_visit_double!(visit_i32 i32, true);
+impl_visit_double!(visit_u32 u32, true);
+impl_visit_double!(visit_i64 i64, true);
+impl_visit_double!(visit_u64 u64, true);
+
+/// Passes doubles without a fractional part to `visit_i64`
+pub(super) struct IntegralDouble;
+
+impl VisitDouble for IntegralDouble {
+    const INTEGER: bool = false;
+
+    fn fits(_: f64) -> bool {
+        true
+    }
+
+    fn visit_double<'de, V: serde::de::Visitor<'de>>(
+        visitor: V,
+        v: f64,
+    ) -> Result<V::Value, Error> {
+        // i64::MIN is exact as a double, i64::MAX rounds up to 2^63
+        if v.fract() == 0.0 && v >= i64::MIN as f64 && v < i64::MAX as f64 {
+            visitor.visit_i64(v as i64)
+        } else {
+            visitor.visit_f64(v)
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Streaming conversion from the AMF3 deserializer into another [`Serializer`]
+//!
+//! This drives the serializer directly from the visitor callbacks of the
+//! deserializer, so there is no intermediate [`Value`](crate::Value). Unlike
+//! `serde_transcode`, a decoding error comes out as it happened, instead of
+//! being converted into a message at every level of nesting.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt, io,
+};
+
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer},
+};
+
+/// The message of the first serializer error, whose type varies with the nesting
+type State = Cell<Option<String>>;
+
+/// Converts the value of `deserializer` into `serializer`
+///
+/// A serializer error is returned as its message, and anything else as the error
+/// of the deserializer.
+pub(crate) fn transcode<'de, D, S>(
+    deserializer: D,
+    serializer: S,
+) -> Result<S::Ok, TranscodeError<D::Error>>
+where
+    D: Deserializer<'de>,
+    S: Serializer,
+{
+    let state = State::new(None);
+    deserializer
+        .deserialize_any(TranscodeVisitor(serializer, &state))
+        .map_err(|e| match state.take() {
+            Some(message) => TranscodeError::Serialize(message),
+            None => TranscodeError::Decode(e),
+        })
+}
+
+pub(crate) enum TranscodeError<E> {
+    Decode(E),
+    Serialize(String),
+}
+
+/// Remembers the first serializer error, and passes it up as a deserializer error
+fn s2d<E: ser::Error, F: de::Error>(state: &State, e: E) -> F {
+    let first = state.take().unwrap_or_else(|| e.to_string());
+    state.set(Some(first));
+    F::custom("serializing failed")
+}
+
+/// A [`Serialize`] implementation that can be serialized exactly once
+///
+/// The error of the deserializer is kept in `error`, so that it can be returned
+/// by the seed that the deserializer was passed to.
+struct Transcoder<'a, 'de, D: Deserializer<'de>> {
+    deserializer: RefCell<Option<D>>,
+    error: RefCell<Option<D::Error>>,
+    state: &'a State,
+}
+
+impl<'a, 'de, D: Deserializer<'de>> Serialize for Transcoder<'a, 'de, D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let deserializer = self
+            .deserializer
+            .borrow_mut()
+            .take()
+            .ok_or_else(|| ser::Error::custom("Transcoder can only be serialized once"))?;
+        deserializer
+            .deserialize_any(TranscodeVisitor(serializer, self.state))
+            .map_err(|e| {
+                *self.error.borrow_mut() = Some(e);
+                ser::Error::custom("deserializing failed")
+            })
+    }
+}
+
+/// Serializes the value of `deserializer` with `serialize`, keeping the error of either
+fn transcode_nested<'de, D, E>(
+    deserializer: D,
+    state: &State,
+    serialize: impl FnOnce(&Transcoder<'_, 'de, D>) -> Result<(), E>,
+) -> Result<(), D::Error>
+where
+    D: Deserializer<'de>,
+    E: ser::Error,
+{
+    let transcoder = Transcoder {
+        deserializer: RefCell::new(Some(deserializer)),
+        error: RefCell::new(None),
+        state,
+    };
+    serialize(&transcoder).map_err(|e| match transcoder.error.into_inner() {
+        Some(error) => error,
+        None => s2d(state, e),
+    })
+}
+
+struct TranscodeVisitor<'a, S>(S, &'a State);
+
+impl<'a, 'de, S: Serializer> Visitor<'de> for TranscodeVisitor<'a, S> {
+    type Value = S::Ok;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        self.0.serialize_bool(v).map_err(|e| s2d(self.1, e))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        self.0.serialize_i64(v).map_err(|e| s2d(self.1, e))
+    }
+
+    fn visit_u32<E: de::Error>(self, v: u32) -> Result<Self::Value, E> {
+        self.0.serialize_u32(v).map_err(|e| s2d(self.1, e))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        self.0.serialize_u64(v).map_err(|e| s2d(self.1, e))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        self.0.serialize_f64(v).map_err(|e| s2d(self.1, e))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.0.serialize_str(v).map_err(|e| s2d(self.1, e))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        self.0.serialize_bytes(v).map_err(|e| s2d(self.1, e))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.0.serialize_none().map_err(|e| s2d(self.1, e))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let state = self.1;
+        let mut value = None;
+        transcode_nested(deserializer, state, |t| {
+            value = Some(self.0.serialize_some(t)?);
+            Ok::<_, S::Error>(())
+        })?;
+        Ok(value.expect("serialized"))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.0.serialize_unit().map_err(|e| s2d(self.1, e))
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let state = self.1;
+        let mut value = None;
+        transcode_nested(deserializer, state, |t| {
+            value = Some(self.0.serialize_newtype_struct("<unknown>", t)?);
+            Ok::<_, S::Error>(())
+        })?;
+        Ok(value.expect("serialized"))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let state = self.1;
+        let mut s = self
+            .0
+            .serialize_seq(seq.size_hint())
+            .map_err(|e| s2d(state, e))?;
+        while let Some(()) = seq.next_element_seed(SeqSeed(&mut s, state))? {}
+        s.end().map_err(|e| s2d(state, e))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let state = self.1;
+        let mut s = self
+            .0
+            .serialize_map(map.size_hint())
+            .map_err(|e| s2d(state, e))?;
+        while let Some(()) = map.next_key_seed(KeySeed(&mut s, state))? {
+            map.next_value_seed(ValueSeed(&mut s, state))?;
+        }
+        s.end().map_err(|e| s2d(state, e))
+    }
+}
+
+struct SeqSeed<'a, S: 'a>(&'a mut S, &'a State);
+
+impl<'de, 'a, S: SerializeSeq> DeserializeSeed<'de> for SeqSeed<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        transcode_nested(deserializer, self.1, |t| self.0.serialize_element(t))
+    }
+}
+
+struct KeySeed<'a, S: 'a>(&'a mut S, &'a State);
+
+impl<'de, 'a, S: SerializeMap> DeserializeSeed<'de> for KeySeed<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        transcode_nested(deserializer, self.1, |t| self.0.serialize_key(t))
+    }
+}
+
+struct ValueSeed<'a, S: 'a>(&'a mut S, &'a State);
+
+impl<'de, 'a, S: SerializeMap> DeserializeSeed<'de> for ValueSeed<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        transcode_nested(deserializer, self.1, |t| self.0.serialize_value(t))
+    }
+}
+
+/// Keeps the [`io::Error`] of a writer, which a JSON error only carries by kind
+pub(crate) struct CaptureWriter<W> {
+    pub inner: W,
+    pub error: Option<io::Error>,
+}
+
+impl<W: io::Write> io::Write for CaptureWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf).map_err(|e| self.capture(e))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().map_err(|e| self.capture(e))
+    }
+}
+
+impl<W> CaptureWriter<W> {
+    fn capture(&mut self, e: io::Error) -> io::Error {
+        let kind = e.kind();
+        self.error.get_or_insert(e);
+        kind.into()
+    }
+}
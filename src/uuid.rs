@@ -0,0 +1,67 @@
+//! Conversion of a [`Uuid`] from and to a 16-byte AMF3 `ByteArray`
+//!
+//! The bytes are in big-endian (RFC 4122) order, as in [`Uuid::as_bytes`].
+//!
+//! ```
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Message {
+//!     #[serde(with = "serde_amf3::uuid")]
+//!     id: uuid::Uuid,
+//! }
+//! ```
+
+use std::fmt;
+
+use serde::{de::Visitor, Deserializer, Serializer};
+use uuid::Uuid;
+
+struct UuidVisitor;
+
+impl<'de> Visitor<'de> for UuidVisitor {
+    type Value = Uuid;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte array of length 16")
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Uuid::from_slice(v).map_err(|_| E::invalid_length(v.len(), &self))
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+    deserializer.deserialize_bytes(UuidVisitor)
+}
+
+pub fn serialize<S: Serializer>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(uuid.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Message {
+        #[serde(with = "super")]
+        id: Uuid,
+    }
+
+    const ID: Uuid = uuid::uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8");
+
+    #[test]
+    fn test_round_trip() {
+        let message = Message { id: ID };
+        let bytes = crate::serialize(&message).unwrap();
+        assert_eq!(&bytes[6..8], &[0x0C, 0x21]);
+        assert_eq!(&bytes[8..24], ID.as_bytes());
+        assert_eq!(crate::deserialize::<Message>(&bytes), Ok(message));
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        let input = b"\x0A\x0B\x01\x05id\x0C\x05\xAB\xCD\x01";
+        assert!(crate::deserialize::<Message>(input).is_err());
+    }
+}
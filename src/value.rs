@@ -0,0 +1,1078 @@
+//! Schema-less representations of AMF3 data
+
+use std::{borrow::Cow, collections::HashMap, fmt, marker::PhantomData};
+
+use serde::{
+    de::{
+        value::{MapDeserializer, SeqDeserializer},
+        EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{Error, Marker};
+
+/// Newtype name that asks the AMF3 deserializer for the marker of the value
+pub(crate) const VALUE_TOKEN: &str = "$serde_amf3::private::Value";
+/// Newtype name that asks the AMF3 serializer to write the map as an `Array`
+pub(crate) const ASSOC_ARRAY_TOKEN: &str = "$serde_amf3::private::AssocArray";
+/// Newtype name that asks the AMF3 serializer to write the double as a `Date`
+/// Unit struct name that asks the AMF3 serializer to write `Undefined` instead of `Null`
+pub(crate) const UNDEFINED_TOKEN: &str = "$serde_amf3::private::Undefined";
+pub(crate) const DATE_TOKEN: &str = "$serde_amf3::private::Date";
+/// Newtype name that asks the AMF3 serializer to write the map as a `Dictionary`
+pub(crate) const DICTIONARY_TOKEN: &str = "$serde_amf3::private::Dictionary";
+/// Like [`DICTIONARY_TOKEN`], with the weak keys flag set
+pub(crate) const WEAK_DICTIONARY_TOKEN: &str = "$serde_amf3::private::WeakDictionary";
+/// Newtype name of [`Xml`], for the AMF3 serializer and deserializer
+pub(crate) const XML_TOKEN: &str = "$serde_amf3::private::Xml";
+/// Newtype name of [`XmlDoc`], for the AMF3 serializer and deserializer
+pub(crate) const XML_DOC_TOKEN: &str = "$serde_amf3::private::XmlDoc";
+
+/// Whether a newtype with this name wants to know the marker of the value
+pub(crate) fn is_marker_token(name: &str) -> bool {
+    name == VALUE_TOKEN || name == XML_TOKEN || name == XML_DOC_TOKEN
+}
+
+/// An E4X `XML` value, that is encoded as [`Marker::Xml`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xml(pub String);
+
+/// A legacy `flash.xml.XMLDocument`, that is encoded as [`Marker::XmlDoc`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlDoc(pub String);
+
+/// A `flash.utils.Dictionary`, whose keys may be any value, e.g. objects
+///
+/// The entries are kept as pairs, because such keys can't be used in a `HashMap`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dictionary<K, V> {
+    pub entries: Vec<(K, V)>,
+    pub weak_keys: bool,
+}
+
+/// An owned AMF3 value
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Value {
+    #[default]
+    Undefined,
+    Null,
+    Bool(bool),
+    Integer(i32),
+    Double(f64),
+    String(String),
+    Xml(String),
+    XmlDoc(String),
+    ByteArray(Vec<u8>),
+    Array(Vec<Value>),
+    /// An `Array` with associative keys, any dense entries use their index as key
+    AssocArray(Vec<(String, Value)>),
+    Object(Vec<(String, Value)>),
+}
+
+/// An AMF3 value that borrows strings and byte arrays from the input
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'de> {
+    Undefined,
+    Null,
+    Bool(bool),
+    Integer(i32),
+    Double(f64),
+    String(&'de str),
+    Xml(&'de str),
+    XmlDoc(&'de str),
+    ByteArray(&'de [u8]),
+    Array(Vec<ValueRef<'de>>),
+    AssocArray(Vec<(Cow<'de, str>, ValueRef<'de>)>),
+    Object(Vec<(Cow<'de, str>, ValueRef<'de>)>),
+}
+
+impl<'de> ValueRef<'de> {
+    pub fn into_owned(self) -> Value {
+        match self {
+            ValueRef::Undefined => Value::Undefined,
+            ValueRef::Null => Value::Null,
+            ValueRef::Bool(v) => Value::Bool(v),
+            ValueRef::Integer(v) => Value::Integer(v),
+            ValueRef::Double(v) => Value::Double(v),
+            ValueRef::String(v) => Value::String(v.to_owned()),
+            ValueRef::Xml(v) => Value::Xml(v.to_owned()),
+            ValueRef::XmlDoc(v) => Value::XmlDoc(v.to_owned()),
+            ValueRef::ByteArray(v) => Value::ByteArray(v.to_owned()),
+            ValueRef::Array(v) => Value::Array(v.into_iter().map(ValueRef::into_owned).collect()),
+            ValueRef::AssocArray(v) => Value::AssocArray(into_owned_entries(v)),
+            ValueRef::Object(v) => Value::Object(into_owned_entries(v)),
+        }
+    }
+}
+
+fn into_owned_entries(entries: Vec<(Cow<str>, ValueRef)>) -> Vec<(String, Value)> {
+    entries
+        .into_iter()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect()
+}
+
+impl Value {
+    /// Encodes this value as AMF3, e.g. to build test payloads
+    pub fn to_amf3_bytes(&self) -> Result<Vec<u8>, Error> {
+        crate::serialize(self)
+    }
+
+    /// Calls `f` on this value and then on all values inside it, depth-first
+    ///
+    /// Values that `f` puts in place of another one are visited as well.
+    pub fn visit_mut(&mut self, f: &mut dyn FnMut(&mut Value)) {
+        f(self);
+        match self {
+            Value::Array(values) => values.iter_mut().for_each(|value| value.visit_mut(f)),
+            Value::AssocArray(entries) | Value::Object(entries) => {
+                entries.iter_mut().for_each(|(_, value)| value.visit_mut(f))
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Value {
+    type Error = Error;
+
+    fn try_from(input: &'a [u8]) -> Result<Self, Self::Error> {
+        crate::deserialize(input)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for ValueRef<'a> {
+    type Error = Error;
+
+    fn try_from(input: &'a [u8]) -> Result<Self, Self::Error> {
+        crate::deserialize(input)
+    }
+}
+
+impl<'de> From<ValueRef<'de>> for Value {
+    fn from(value: ValueRef<'de>) -> Self {
+        value.into_owned()
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Undefined => serializer.serialize_unit_struct(UNDEFINED_TOKEN),
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Integer(v) => serializer.serialize_i32(*v),
+            Value::Double(v) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Xml(v) => serializer.serialize_newtype_struct(XML_TOKEN, v),
+            Value::XmlDoc(v) => serializer.serialize_newtype_struct(XML_DOC_TOKEN, v),
+            Value::ByteArray(v) => serializer.serialize_bytes(v),
+            Value::Array(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for value in v {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Value::AssocArray(v) => {
+                serializer.serialize_newtype_struct(ASSOC_ARRAY_TOKEN, &Entries(v))
+            }
+            Value::Object(v) => Entries(v).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Serialize for ValueRef<'de> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ValueRef::Undefined => serializer.serialize_unit_struct(UNDEFINED_TOKEN),
+            ValueRef::Null => serializer.serialize_unit(),
+            ValueRef::Bool(v) => serializer.serialize_bool(*v),
+            ValueRef::Integer(v) => serializer.serialize_i32(*v),
+            ValueRef::Double(v) => serializer.serialize_f64(*v),
+            ValueRef::String(v) => serializer.serialize_str(v),
+            ValueRef::Xml(v) => serializer.serialize_newtype_struct(XML_TOKEN, v),
+            ValueRef::XmlDoc(v) => serializer.serialize_newtype_struct(XML_DOC_TOKEN, v),
+            ValueRef::ByteArray(v) => serializer.serialize_bytes(v),
+            ValueRef::Array(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for value in v {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            ValueRef::AssocArray(v) => {
+                serializer.serialize_newtype_struct(ASSOC_ARRAY_TOKEN, &Entries(v))
+            }
+            ValueRef::Object(v) => Entries(v).serialize(serializer),
+        }
+    }
+}
+
+/// Serializes key-value pairs as a map
+struct Entries<'a, K, V>(&'a [(K, V)]);
+
+impl<'a, K: Serialize, V: Serialize> Serialize for Entries<'a, K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Map keys may also be the indices of the dense portion of an array
+struct KeyVisitor;
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string or index key")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(Cow::Borrowed(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v.to_string()))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v.to_string()))
+    }
+}
+
+pub(crate) struct Key<'de>(pub(crate) Cow<'de, str>);
+
+impl<'de> Deserialize<'de> for Key<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(KeyVisitor).map(Key)
+    }
+}
+
+/// Deserializes the content of a value with `deserialize_any`
+struct AnySeed<V>(V);
+
+impl<'de, V: Visitor<'de>> serde::de::DeserializeSeed<'de> for AnySeed<V> {
+    type Value = V::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self.0)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an AMF3 value")
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Undefined)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        // only used by the AMF3 deserializer, to tell arrays from objects
+        let (marker, variant): (u8, _) = data.variant()?;
+        let value = variant.newtype_variant_seed(AnySeed(ValueVisitor))?;
+        Ok(match value {
+            Value::Object(v) if marker == Marker::Array as u8 => Value::AssocArray(v),
+            Value::String(v) if marker == Marker::Xml as u8 => Value::Xml(v),
+            Value::String(v) if marker == Marker::XmlDoc as u8 => Value::XmlDoc(v),
+            value => value,
+        })
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(i32::try_from(v)
+            .map(Value::Integer)
+            .unwrap_or(Value::Double(v as f64)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(i32::try_from(v)
+            .map(Value::Integer)
+            .unwrap_or(Value::Double(v as f64)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Value::Double(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Value::ByteArray(v.to_owned()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Value::ByteArray(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((Key(key), value)) = map.next_entry()? {
+            entries.push((key.into_owned(), value));
+        }
+        Ok(Value::Object(entries))
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(VALUE_TOKEN, ValueVisitor)
+    }
+}
+
+struct ValueRefVisitor;
+
+impl<'de> Visitor<'de> for ValueRefVisitor {
+    type Value = ValueRef<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an AMF3 value borrowed from the input")
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(ValueRef::Undefined)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(ValueRef::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        ValueRef::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        // only used by the AMF3 deserializer, to tell arrays from objects
+        let (marker, variant): (u8, _) = data.variant()?;
+        let value = variant.newtype_variant_seed(AnySeed(ValueRefVisitor))?;
+        Ok(match value {
+            ValueRef::Object(v) if marker == Marker::Array as u8 => ValueRef::AssocArray(v),
+            ValueRef::String(v) if marker == Marker::Xml as u8 => ValueRef::Xml(v),
+            ValueRef::String(v) if marker == Marker::XmlDoc as u8 => ValueRef::XmlDoc(v),
+            value => value,
+        })
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(ValueRef::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(i32::try_from(v)
+            .map(ValueRef::Integer)
+            .unwrap_or(ValueRef::Double(v as f64)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(i32::try_from(v)
+            .map(ValueRef::Integer)
+            .unwrap_or(ValueRef::Double(v as f64)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(ValueRef::Double(v))
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(ValueRef::String(v))
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(ValueRef::ByteArray(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(ValueRef::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((Key(key), value)) = map.next_entry()? {
+            entries.push((key, value));
+        }
+        Ok(ValueRef::Object(entries))
+    }
+}
+
+impl<'de> Deserialize<'de> for ValueRef<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(VALUE_TOKEN, ValueRefVisitor)
+    }
+}
+
+/// An AMF3 `Array` split into its dense and its associative portion
+///
+/// A purely dense array has no associative entries, and an object has no dense items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixedArray<T, U> {
+    pub dense: Vec<T>,
+    pub assoc: HashMap<String, U>,
+}
+
+/// The key of an entry in a [`MixedArray`]
+enum MixedKey {
+    Index,
+    Name(String),
+}
+
+impl<'de> Deserialize<'de> for MixedKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MixedKeyVisitor)
+    }
+}
+
+struct MixedKeyVisitor;
+
+impl<'de> Visitor<'de> for MixedKeyVisitor {
+    type Value = MixedKey;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string or index key")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(MixedKey::Name(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(MixedKey::Name(v))
+    }
+
+    fn visit_u64<E>(self, _v: u64) -> Result<Self::Value, E> {
+        // dense items come in order
+        Ok(MixedKey::Index)
+    }
+}
+
+struct MixedArrayVisitor<T, U>(PhantomData<(T, U)>);
+
+impl<'de, T: Deserialize<'de>, U: Deserialize<'de>> Visitor<'de> for MixedArrayVisitor<T, U> {
+    type Value = MixedArray<T, U>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut dense = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            dense.push(value);
+        }
+        Ok(MixedArray {
+            dense,
+            assoc: HashMap::new(),
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut dense = Vec::new();
+        let mut assoc = HashMap::new();
+        while let Some(key) = map.next_key()? {
+            match key {
+                MixedKey::Index => dense.push(map.next_value()?),
+                MixedKey::Name(name) => {
+                    assoc.insert(name, map.next_value()?);
+                }
+            }
+        }
+        Ok(MixedArray { dense, assoc })
+    }
+}
+
+impl<'de, T: Deserialize<'de>, U: Deserialize<'de>> Deserialize<'de> for MixedArray<T, U> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MixedArrayVisitor(PhantomData))
+    }
+}
+
+/// Accepts a string with the marker `expected`, or any string from other formats
+struct XmlVisitor {
+    expected: Marker,
+}
+
+impl<'de> Visitor<'de> for XmlVisitor {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a value with marker {:?}", self.expected)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(v.to_owned())
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_string(self)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (marker, variant): (u8, _) = data.variant()?;
+        if marker != self.expected as u8 {
+            return Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(marker.into()),
+                &self,
+            ));
+        }
+        variant.newtype_variant()
+    }
+}
+
+impl Serialize for Xml {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(XML_TOKEN, &self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Xml {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let visitor = XmlVisitor {
+            expected: Marker::Xml,
+        };
+        deserializer
+            .deserialize_newtype_struct(XML_TOKEN, visitor)
+            .map(Xml)
+    }
+}
+
+impl Serialize for XmlDoc {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(XML_DOC_TOKEN, &self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for XmlDoc {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let visitor = XmlVisitor {
+            expected: Marker::XmlDoc,
+        };
+        deserializer
+            .deserialize_newtype_struct(XML_DOC_TOKEN, visitor)
+            .map(XmlDoc)
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for Dictionary<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = if self.weak_keys {
+            WEAK_DICTIONARY_TOKEN
+        } else {
+            DICTIONARY_TOKEN
+        };
+        serializer.serialize_newtype_struct(name, &Entries(&self.entries))
+    }
+}
+
+impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de> for Dictionary<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(DICTIONARY_TOKEN, DictionaryVisitor(PhantomData))
+    }
+}
+
+struct DictionaryVisitor<K, V>(PhantomData<(K, V)>);
+
+impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> Visitor<'de> for DictionaryVisitor<K, V> {
+    type Value = Dictionary<K, V>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a dictionary or a sequence of key-value pairs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let entries = Vec::deserialize(serde::de::value::SeqAccessDeserializer::new(seq))?;
+        Ok(Dictionary {
+            entries,
+            weak_keys: false,
+        })
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        // the AMF3 deserializer passes the weak keys flag, then the entries as pairs
+        let (weak_keys, variant): (bool, _) = data.variant()?;
+        let Dictionary { entries, .. } = variant.tuple_variant(2, self)?;
+        Ok(Dictionary { entries, weak_keys })
+    }
+}
+
+/// A [`Deserializer`] that reads from an already decoded [`Value`]
+pub struct ValueDeserializer {
+    value: Value,
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = ValueDeserializer;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        ValueDeserializer { value: self }
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Undefined => visitor.visit_none(),
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::Integer(v) => visitor.visit_i32(v),
+            Value::Double(v) => visitor.visit_f64(v),
+            Value::String(v) | Value::Xml(v) | Value::XmlDoc(v) => visitor.visit_string(v),
+            Value::ByteArray(v) => visitor.visit_byte_buf(v),
+            Value::Array(v) => {
+                let mut seq = SeqDeserializer::new(v.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::AssocArray(v) | Value::Object(v) => {
+                let mut map = MapDeserializer::new(v.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Undefined | Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if is_marker_token(name) {
+            // keep associative arrays apart from objects, and XML apart from strings
+            visitor.visit_enum(self)
+        } else {
+            visitor.visit_newtype_struct(self)
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::String(v) => visitor.visit_enum(v.into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+}
+
+impl<'de> EnumAccess<'de> for ValueDeserializer {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<T>(self, seed: T) -> Result<(T::Value, Self::Variant), Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        let marker = match self.value {
+            Value::AssocArray(_) => Marker::Array,
+            Value::Xml(_) => Marker::Xml,
+            Value::XmlDoc(_) => Marker::XmlDoc,
+            Value::String(_) => Marker::String,
+            _ => Marker::Object,
+        };
+        let deserializer = serde::de::value::U8Deserializer::<Error>::new(marker as u8);
+        Ok((seed.deserialize(deserializer)?, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(serde::de::Error::custom("expected a newtype variant"))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("expected a newtype variant"))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("expected a newtype variant"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, collections::HashMap};
+
+    use serde::{de::IntoDeserializer, Deserialize, Serialize};
+
+    use super::{Dictionary, MixedArray, Value, ValueRef, Xml, XmlDoc};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Test {
+        a: u32,
+        b: Vec<String>,
+    }
+
+    #[test]
+    fn test_value_ref_string() {
+        let input = b"\x06\x0BHello";
+        let value = crate::deserialize::<ValueRef>(input).unwrap();
+        let ValueRef::String(string) = value else {
+            panic!("expected a string, got {:?}", value);
+        };
+        assert_eq!(string, "Hello");
+        assert_eq!(string.as_ptr(), input[2..].as_ptr());
+    }
+
+    #[test]
+    fn test_try_from() {
+        let input = [0x09, 0x7, 0x01, 0x04, 1, 0x04, 2, 0x04, 3];
+        let values = vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)];
+        assert_eq!(Value::try_from(&input[..]), Ok(Value::Array(values)));
+        let input = [0x09, 0x03, 0x01, 0x06, 0x03, b'a'];
+        assert_eq!(
+            ValueRef::try_from(&input[..]),
+            Ok(ValueRef::Array(vec![ValueRef::String("a")]))
+        );
+        assert!(Value::try_from(&input[..5]).is_err());
+    }
+
+    #[test]
+    fn test_value_ref_nested() {
+        let input = [0x09, 0x05, 0x01, 0x06, 0x03, b'a', 0x0C, 0x05, 0xAB, 0xCD];
+        assert_eq!(
+            crate::deserialize::<ValueRef>(&input),
+            Ok(ValueRef::Array(vec![
+                ValueRef::String("a"),
+                ValueRef::ByteArray(&[0xAB, 0xCD]),
+            ]))
+        );
+        let input = [0x09, 0x01, 0x03, b'a', 0x04, 5, 0x03, b'b', 0x00, 0x01];
+        let value = crate::deserialize::<ValueRef>(&input).unwrap();
+        assert_eq!(
+            value,
+            ValueRef::AssocArray(vec![
+                (Cow::Borrowed("a"), ValueRef::Integer(5)),
+                (Cow::Borrowed("b"), ValueRef::Undefined),
+            ])
+        );
+        assert_eq!(
+            value.into_owned(),
+            Value::AssocArray(vec![
+                (String::from("a"), Value::Integer(5)),
+                (String::from("b"), Value::Undefined),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_to_amf3_bytes() {
+        let value = Value::Object(vec![
+            (String::from("a"), Value::Integer(5)),
+            (
+                String::from("b"),
+                Value::Array(vec![Value::String(String::from("Hello"))]),
+            ),
+        ]);
+        let bytes = value.to_amf3_bytes().unwrap();
+        assert_eq!(
+            crate::deserialize::<Test>(&bytes),
+            Ok(Test {
+                a: 5,
+                b: vec![String::from("Hello")],
+            })
+        );
+        assert_eq!(crate::deserialize::<Value>(&bytes), Ok(value));
+    }
+
+    #[test]
+    fn test_undefined_null_in_map() {
+        let map = HashMap::from([
+            (String::from("undefined"), Value::Undefined),
+            (String::from("null"), Value::Null),
+        ]);
+        let bytes = crate::serialize(&map).unwrap();
+        assert!(bytes.windows(11).any(|w| w == b"\x13undefined\x00"));
+        assert!(bytes.windows(6).any(|w| w == b"\x09null\x01"));
+        assert_eq!(
+            crate::deserialize::<HashMap<String, Value>>(&bytes),
+            Ok(map)
+        );
+    }
+
+    #[test]
+    fn test_none_is_null() {
+        assert_eq!(crate::serialize(&None::<u32>), Ok(vec![0x01]));
+        assert_eq!(crate::serialize(&Value::Undefined), Ok(vec![0x00]));
+        assert_eq!(crate::serialize(&ValueRef::Undefined), Ok(vec![0x00]));
+        assert_eq!(crate::deserialize::<Option<u32>>(&[0x01]), Ok(None));
+    }
+
+    #[test]
+    fn test_assoc_array_round_trip() {
+        let input = [0x09, 0x01, 0x03, b'a', 0x04, 5, 0x01];
+        let value = crate::deserialize::<Value>(&input).unwrap();
+        assert_eq!(
+            value,
+            Value::AssocArray(vec![(String::from("a"), Value::Integer(5))])
+        );
+        assert_eq!(value.to_amf3_bytes().unwrap(), input);
+
+        let input = [0x0A, 0x0B, 0x01, 0x03, b'a', 0x04, 5, 0x01];
+        let value = crate::deserialize::<Value>(&input).unwrap();
+        assert_eq!(
+            value,
+            Value::Object(vec![(String::from("a"), Value::Integer(5))])
+        );
+        assert_eq!(value.to_amf3_bytes().unwrap(), input);
+    }
+
+    #[test]
+    fn test_into_deserializer() {
+        let value = Value::Object(vec![
+            (String::from("a"), Value::Integer(5)),
+            (
+                String::from("b"),
+                Value::Array(vec![Value::String(String::from("Hello"))]),
+            ),
+        ]);
+        assert_eq!(
+            Test::deserialize(value.into_deserializer()),
+            Ok(Test {
+                a: 5,
+                b: vec![String::from("Hello")],
+            })
+        );
+        let value = Value::AssocArray(vec![(String::from("a"), Value::Null)]);
+        assert_eq!(
+            Value::deserialize(value.clone().into_deserializer()),
+            Ok(value)
+        );
+    }
+
+    #[test]
+    fn test_xml_round_trip() {
+        let xml = Xml(String::from("<a/>"));
+        let bytes = crate::serialize(&xml).unwrap();
+        assert_eq!(bytes, b"\x0B\x09<a/>");
+        assert_eq!(crate::deserialize::<Xml>(&bytes), Ok(xml));
+        assert_eq!(
+            crate::deserialize::<Value>(&bytes),
+            Ok(Value::Xml(String::from("<a/>")))
+        );
+
+        let xml = XmlDoc(String::from("<b/>"));
+        let bytes = crate::serialize(&xml).unwrap();
+        assert_eq!(bytes, b"\x07\x09<b/>");
+        assert_eq!(crate::deserialize::<XmlDoc>(&bytes), Ok(xml));
+        let value = crate::deserialize::<Value>(&bytes).unwrap();
+        assert_eq!(value, Value::XmlDoc(String::from("<b/>")));
+        assert_eq!(value.to_amf3_bytes(), Ok(bytes.clone()));
+
+        // the marker tells them apart
+        assert!(crate::deserialize::<Xml>(&bytes).is_err());
+        assert!(crate::deserialize::<Xml>(b"\x06\x09<a/>").is_err());
+
+        // a length past the end of the input
+        assert!(crate::deserialize::<Xml>(b"\x0B\x09<a").is_err());
+    }
+
+    #[test]
+    fn test_mixed_array() {
+        let input = b"\x09\x05\x03a\x06\x03x\x03b\x06\x03y\x01\x04\x01\x04\x02";
+        let array = crate::deserialize::<MixedArray<u32, String>>(input).unwrap();
+        assert_eq!(array.dense, vec![1, 2]);
+        assert_eq!(array.assoc.len(), 2);
+        assert_eq!(array.assoc["a"], "x");
+        assert_eq!(array.assoc["b"], "y");
+
+        let array = crate::deserialize::<MixedArray<u32, String>>(b"\x09\x05\x01\x04\x01\x04\x02");
+        assert_eq!(array.map(|a| a.dense), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_no_panic() {
+        // every prefix must decode to a value or fail with an error
+        for first in 0..=u8::MAX {
+            let _ = crate::deserialize::<Value>(&[first]);
+            let _ = crate::deserialize::<ValueRef>(&[first]);
+            for second in 0..=u8::MAX {
+                let _ = crate::deserialize::<Value>(&[first, second]);
+                let _ = crate::deserialize::<ValueRef>(&[first, second]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_visit_mut() {
+        let input = b"\x0A\x0B\x01\x09name\x06\x07Bob\x0Bitems\x09\x05\x01\x06\x03a\x04\x05\x01";
+        let mut value = crate::deserialize::<Value>(input).unwrap();
+        let mut count = 0;
+        value.visit_mut(&mut |value| {
+            count += 1;
+            if let Value::String(v) = value {
+                *v = String::from("REDACTED");
+            }
+        });
+        assert_eq!(count, 5);
+        let bytes = value.to_amf3_bytes().unwrap();
+        assert_eq!(
+            crate::deserialize::<Value>(&bytes),
+            Ok(Value::Object(vec![
+                (
+                    String::from("name"),
+                    Value::String(String::from("REDACTED"))
+                ),
+                (
+                    String::from("items"),
+                    Value::Array(vec![
+                        Value::String(String::from("REDACTED")),
+                        Value::Integer(5)
+                    ])
+                ),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_dictionary_round_trip() {
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+        struct Point {
+            x: u32,
+        }
+
+        let dictionary = Dictionary {
+            entries: vec![(Point { x: 1 }, 7), (Point { x: 2 }, 8)],
+            weak_keys: true,
+        };
+        let bytes = crate::serialize(&dictionary).unwrap();
+        // two entries, weak keys, then the first key
+        assert_eq!(&bytes[..4], b"\x11\x05\x01\x0A");
+        assert_eq!(crate::deserialize(&bytes), Ok(dictionary.clone()));
+        // a referenced dictionary keeps its flag
+        let mut input = b"\x09\x05\x01".to_vec();
+        input.extend_from_slice(&bytes);
+        input.extend_from_slice(b"\x11\x02");
+        assert_eq!(
+            crate::deserialize::<Vec<Dictionary<Point, u32>>>(&input),
+            Ok(vec![dictionary.clone(), dictionary])
+        );
+        assert!(crate::deserialize::<Dictionary<u32, u32>>(b"\x11\x01\x02").is_err());
+
+        // a reference to an array, not a dictionary
+        let input = b"\x09\x05\x01\x09\x01\x01\x11\x02";
+        let error = crate::deserialize::<(Vec<u32>, Dictionary<u32, u32>)>(input).unwrap_err();
+        assert_eq!(error.to_string(), "Expected marker Dictionary, found Array");
+
+        let dictionary = Dictionary {
+            entries: vec![(Value::Integer(1), String::from("a"))],
+            weak_keys: false,
+        };
+        let bytes = crate::serialize(&dictionary).unwrap();
+        assert_eq!(bytes, b"\x11\x03\x00\x04\x01\x06\x03a");
+        assert_eq!(crate::deserialize(&bytes), Ok(dictionary));
+    }
+}
@@ -0,0 +1,79 @@
+//! Counts allocations while decoding byte arrays
+//!
+//! This replaces the global allocator, so it has its own test binary.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+/// Counts the allocations of the current thread
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    let _ = ALLOCATIONS.try_with(|c| c.set(c.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations(f: &dyn Fn()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn test_large_byte_array() {
+    // 512 KiB, with a length of (1 << 19) in the u29 header
+    const LEN: usize = 1 << 19;
+    let mut input = vec![0x0C, 0xC0, 0x80, 0x01];
+    input.resize(input.len() + LEN, 0xAB);
+
+    // only the entry in the object reference table, same as for an empty array
+    let empty = allocations(&|| {
+        let bytes = serde_amf3::deserialize::<&[u8]>(&[0x0C, 0x01]).unwrap();
+        assert!(bytes.is_empty());
+    });
+    let borrowed = allocations(&|| {
+        let bytes = serde_amf3::deserialize::<&[u8]>(&input).unwrap();
+        assert_eq!(bytes.len(), LEN);
+    });
+    assert_eq!(borrowed, empty);
+
+    let owned = allocations(&|| {
+        let bytes = serde_amf3::deserialize::<Vec<u8>>(&input).unwrap();
+        assert_eq!(bytes.len(), LEN);
+        assert_eq!(bytes.capacity(), LEN);
+    });
+    assert_eq!(owned, borrowed + 1);
+
+    // the length is checked before reserving anything
+    assert!(serde_amf3::deserialize::<Vec<u8>>(&input[..LEN]).is_err());
+
+    let input = [0x09, 0x05, 0x01, 0x0C, 0x05, 0xAB, 0xCD, 0x0C, 0x02];
+    assert_eq!(
+        serde_amf3::deserialize::<Vec<Vec<u8>>>(&input),
+        Ok(vec![vec![0xAB, 0xCD]; 2])
+    );
+}